@@ -109,6 +109,12 @@ pub fn day15_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("day15");
     group.sample_size(10);
     group.measurement_time(Duration::from_secs(60));
+
+    // The whole 30 million turn game, without the parsing
+    let seed = day15::parse_input();
+    group.bench_function("part2", |b| {
+        b.iter(|| day15::MemoryGame::new(&seed, 30_000_000).nth_turn(30_000_000))
+    });
     group.bench_function("solve", |b| b.iter(day15::solve));
     group.finish();
 }
@@ -138,7 +144,21 @@ pub fn day18_benchmark(c: &mut Criterion) {
 }
 
 pub fn day19_benchmark(c: &mut Criterion) {
-    c.bench_function("day19", |b| b.iter(day19::solve));
+    let mut group = c.benchmark_group("day19");
+
+    group.bench_function("parsing", |b| b.iter(day19::parse_input));
+
+    let (grammar, messages) = day19::parse_input();
+
+    group.bench_function("general", |b| {
+        b.iter(|| day19::solve_general(grammar.clone(), &messages))
+    });
+
+    group.bench_function("bitpacked", |b| {
+        b.iter(|| day19::solve_bitpacked(&grammar, &messages))
+    });
+
+    group.finish();
 }
 
 pub fn day20_benchmark(c: &mut Criterion) {
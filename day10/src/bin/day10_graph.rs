@@ -1,27 +1,20 @@
-const COLORS: [&str; 3] = ["red", "green", "blue"];
+use day10::{parse_input, AdapterChain, MAX_STEP};
 
-fn main() {
-    let mut adapters = [false; 256];
-
-    let highest = include_str!("../input.txt")
-        .lines()
-        .map(|n| n.parse::<u8>().unwrap())
-        .inspect(|&n| adapters[n as usize] = true)
-        .max()
-        .unwrap();
+const COLORS: [&str; MAX_STEP as usize] = ["red", "green", "blue"];
 
-    adapters[0] = true;
-    adapters[highest as usize + 3] = true;
+fn main() {
+    let chain = AdapterChain::new(&parse_input(), MAX_STEP);
+    let joltages = chain.joltages();
 
     println!("digraph {{");
 
     println!("0 [shape=box]");
-    println!("{} [shape=box]", highest + 3);
+    println!("{} [shape=box]", chain.device());
 
-    for (n, _) in adapters.iter().enumerate().filter(|&(_, &val)| val) {
-        for d in 1..=3 {
-            if adapters[n + d] {
-                println!("{} -> {} [color={}]", n, n + d, COLORS[d - 1]);
+    for (i, &n) in joltages.iter().enumerate() {
+        for &m in joltages[i + 1..].iter().take_while(|&&m| m - n <= MAX_STEP) {
+            if m != n {
+                println!("{} -> {} [color={}]", n, m, COLORS[(m - n) as usize - 1]);
             }
         }
     }
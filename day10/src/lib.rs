@@ -1,45 +1,113 @@
-fn solve_part1(adapters: &[u8]) -> usize {
-    let mut ones = 0;
-    let mut threes = 0;
-
-    for (a, b) in adapters.iter().zip(adapters.iter().skip(1)) {
-        match b - a {
-            1 => ones += 1,
-            3 => threes += 1,
-            _ => {}
+use std::collections::BTreeMap;
+
+/// How much lower an adapter's input can be than its output in the puzzle
+pub const MAX_STEP: u64 = 3;
+
+/// Every adapter in the bag plugged in from lowest to highest, between the
+/// charging outlet and the device
+#[derive(Debug, Clone)]
+pub struct AdapterChain {
+    /// Sorted, starting with the outlet and ending with the device
+    joltages: Vec<u64>,
+    max_step: u64,
+}
+
+impl AdapterChain {
+    /// Adapters can take an input up to `max_step` jolts lower than their
+    /// output, and so can the device, whose rating is `max_step` jolts higher
+    /// than the highest adapter
+    pub fn new(adapters: &[u64], max_step: u64) -> Self {
+        assert!(max_step > 0, "adapters must step the joltage up");
+
+        let mut joltages = Vec::with_capacity(adapters.len() + 2);
+        joltages.push(0);
+        joltages.extend_from_slice(adapters);
+        joltages.sort_unstable();
+        joltages.push(joltages.last().unwrap() + max_step);
+
+        Self { joltages, max_step }
+    }
+
+    pub fn max_step(&self) -> u64 {
+        self.max_step
+    }
+
+    /// The outlet, every adapter and the device, from lowest to highest
+    pub fn joltages(&self) -> &[u64] {
+        &self.joltages
+    }
+
+    pub fn device(&self) -> u64 {
+        *self.joltages.last().unwrap()
+    }
+
+    /// How many times each difference in joltage shows up when using every
+    /// adapter. Differences of 0 or over `max_step` mean that the chain
+    /// doesn't actually work.
+    pub fn differences(&self) -> BTreeMap<u64, usize> {
+        let mut histogram = BTreeMap::new();
+        for pair in self.joltages.windows(2) {
+            *histogram.entry(pair[1] - pair[0]).or_insert(0) += 1;
         }
+        histogram
     }
 
-    ones * threes
-}
+    /// Can every adapter be used at once?
+    pub fn is_valid(&self) -> bool {
+        self.joltages
+            .windows(2)
+            .all(|pair| (1..=self.max_step).contains(&(pair[1] - pair[0])))
+    }
 
-fn solve_part2(adapters: &[u8]) -> u64 {
-    let mut edges = [0; 256];
-    edges[0] = 1;
-    edges[1] = 1;
-    edges[2] = 2;
-    debug_assert!(adapters.contains(&0) && adapters.contains(&1) && adapters.contains(&2));
+    /// How many ways there are to connect the outlet to the device using some
+    /// of the adapters
+    pub fn arrangements(&self) -> u128 {
+        // ways[i] is how many ways there are to get to the ith joltage. Only
+        // the joltages less than `max_step` lower can lead to it, and since
+        // they're sorted those are a window ending just before it.
+        let mut ways = vec![0u128; self.joltages.len()];
+        ways[0] = 1;
+        let mut start = 0;
 
-    for &n in adapters.iter().skip(3) {
-        edges[n as usize] =
-            edges[(n - 1) as usize] + edges[(n - 2) as usize] + edges[(n - 3) as usize];
+        for i in 1..self.joltages.len() {
+            let n = self.joltages[i];
+            while n - self.joltages[start] > self.max_step {
+                start += 1;
+            }
+
+            ways[i] = (start..i)
+                .filter(|&j| self.joltages[j] < n)
+                .map(|j| ways[j])
+                .sum();
+        }
+
+        *ways.last().unwrap()
     }
+}
 
-    edges[*adapters.iter().max().unwrap() as usize]
+fn solve_part1(chain: &AdapterChain) -> usize {
+    let differences = chain.differences();
+    let count = |d| differences.get(&d).copied().unwrap_or(0);
+    count(1) * count(3)
 }
 
-#[inline]
-pub fn solve() -> (usize, u64) {
-    let mut adapters = include_str!("input.txt")
+fn solve_part2(chain: &AdapterChain) -> u128 {
+    chain.arrangements()
+}
+
+pub fn parse_input() -> Vec<u64> {
+    include_str!("input.txt")
         .lines()
-        .map(|n| n.parse::<u8>().unwrap())
-        .collect::<Vec<_>>();
-    adapters.sort_unstable();
-    adapters.insert(0, 0);
-    adapters.push(adapters.last().unwrap() + 3);
-
-    let part1 = solve_part1(&adapters);
-    let part2 = solve_part2(&adapters);
+        .map(|n| n.parse::<u64>().unwrap())
+        .collect::<Vec<_>>()
+}
+
+#[inline]
+pub fn solve() -> (usize, u128) {
+    let chain = AdapterChain::new(&parse_input(), MAX_STEP);
+
+    let part1 = solve_part1(&chain);
+    let part2 = solve_part2(&chain);
 
     (part1, part2)
 }
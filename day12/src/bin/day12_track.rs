@@ -0,0 +1,36 @@
+//! Export the route the ship takes as SVG or CSV
+//!
+//! Usage: day12_track [1|2] [svg|csv] [FILE]
+//!
+//! Part 1 navigates by heading and part 2 by waypoint. Defaults to part 2 as SVG,
+//! using the puzzle input unless given a file, whose turns can be by any angle.
+
+use std::{env, fs};
+
+use day12::{parse_directions, parse_input, render, Mode, Ship};
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let mode = match args.next().as_deref() {
+        Some("1") => Mode::Heading,
+        Some("2") | None => Mode::Waypoint,
+        Some(other) => panic!("unknown part {:?}, expected 1 or 2", other),
+    };
+
+    let format = args.next();
+
+    let ship = match args.next() {
+        Some(path) => {
+            let input = fs::read_to_string(path).expect("couldn't read the file");
+            Ship::new(mode).run(parse_directions(&input))
+        }
+        None => Ship::new(mode).run(parse_input()),
+    };
+
+    match format.as_deref() {
+        Some("svg") | None => print!("{}", render::svg(&ship)),
+        Some("csv") => print!("{}", render::csv(&ship)),
+        Some(other) => panic!("unknown format {:?}, expected svg or csv", other),
+    }
+}
@@ -1,95 +1,194 @@
 use std::mem::swap;
 
+pub mod render;
+
 //                                    E       S         W        N
 const ANGLES: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Direction {
     Move(isize, isize),
+    /// Quarter turns clockwise, from 0 to 3
     Rotate(isize),
+    /// Degrees clockwise, for turns that aren't a multiple of 90 degrees
+    Turn(isize),
     Forward(isize),
 }
 
-#[inline]
-pub fn solve_part1(directions: impl Iterator<Item = Direction>) -> isize {
-    let mut x = 0;
-    let mut y = 0;
-    let mut a = 0;
-
-    for direction in directions {
-        match direction {
-            Direction::Move(dx, dy) => {
-                x += dx;
-                y += dy;
-            }
+/// What the movement instructions act on
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Mode {
+    /// They move the ship itself, and it turns to face a new heading
+    Heading,
+    /// They move a waypoint relative to the ship, which moves towards it
+    Waypoint,
+}
 
-            Direction::Rotate(da) => a += da,
+/// A ship following navigation instructions, keeping track of everywhere it's been
+///
+/// Positions are floating point so that the ship can turn by any angle. Quarter
+/// turns are still done exactly, so on whole-number routes that only turn by
+/// multiples of 90 degrees the ship never leaves the grid.
+#[derive(Debug, Clone)]
+pub struct Ship {
+    mode: Mode,
+    x: f64,
+    y: f64,
+    /// Degrees clockwise from east
+    heading: f64,
+    /// The waypoint, relative to the ship
+    wx: f64,
+    wy: f64,
+    /// The ship's position before the first instruction and after each one
+    track: Vec<(f64, f64)>,
+    /// Same as `track`, but for the waypoint's absolute position
+    waypoint_track: Vec<(f64, f64)>,
+}
 
-            Direction::Forward(n) => {
-                let (dx, dy) = ANGLES[(a % 4) as usize];
-                x += dx * n;
-                y += dy * n;
-            }
+impl Ship {
+    pub fn new(mode: Mode) -> Self {
+        let mut ship = Self {
+            mode,
+            x: 0.0,
+            y: 0.0,
+            heading: 0.0,
+            wx: 10.0,
+            wy: -1.0,
+            track: Vec::new(),
+            waypoint_track: Vec::new(),
+        };
+        ship.record();
+        ship
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    /// Manhattan distance from the starting point, rounded to the nearest
+    /// whole number
+    pub fn distance(&self) -> isize {
+        (self.x.abs() + self.y.abs()).round() as isize
+    }
+
+    pub fn track(&self) -> &[(f64, f64)] {
+        &self.track
+    }
+
+    /// Where the waypoint was at each step of the track, only meaningful when
+    /// navigating by waypoint
+    pub fn waypoint_track(&self) -> &[(f64, f64)] {
+        &self.waypoint_track
+    }
+
+    fn record(&mut self) {
+        self.track.push((self.x, self.y));
+        self.waypoint_track
+            .push((self.x + self.wx, self.y + self.wy));
+    }
+
+    /// The unit vector the ship is facing
+    fn facing(&self) -> (f64, f64) {
+        let heading = self.heading.rem_euclid(360.0);
+
+        if heading % 90.0 == 0.0 {
+            let (dx, dy) = ANGLES[(heading / 90.0) as usize];
+            (dx as f64, dy as f64)
+        } else {
+            let (sin, cos) = heading.to_radians().sin_cos();
+            (cos, sin)
         }
     }
 
-    x.abs() + y.abs()
-}
+    pub fn step(&mut self, direction: Direction) {
+        match (self.mode, direction) {
+            (Mode::Heading, Direction::Move(dx, dy)) => {
+                self.x += dx as f64;
+                self.y += dy as f64;
+            }
 
-#[inline]
-pub fn solve_part2(directions: impl Iterator<Item = Direction>) -> isize {
-    let mut x: isize = 0;
-    let mut y: isize = 0;
+            (Mode::Heading, Direction::Rotate(da)) => self.heading += 90.0 * da as f64,
+
+            (Mode::Heading, Direction::Turn(degrees)) => self.heading += degrees as f64,
 
-    let mut wx = 10;
-    let mut wy = -1;
+            (Mode::Heading, Direction::Forward(n)) => {
+                let (dx, dy) = self.facing();
+                self.x += dx * n as f64;
+                self.y += dy * n as f64;
+            }
 
-    for direction in directions {
-        match direction {
-            Direction::Move(dx, dy) => {
-                wx += dx;
-                wy += dy;
+            (Mode::Waypoint, Direction::Move(dx, dy)) => {
+                self.wx += dx as f64;
+                self.wy += dy as f64;
             }
 
-            Direction::Rotate(da) => {
+            (Mode::Waypoint, Direction::Rotate(da)) => {
                 for _ in 0..da {
-                    swap(&mut wx, &mut wy);
-                    wx = -wx;
+                    swap(&mut self.wx, &mut self.wy);
+                    self.wx = -self.wx;
                 }
             }
 
-            Direction::Forward(n) => {
-                x += wx * n;
-                y += wy * n;
+            // Since south is positive y, this is the usual rotation matrix
+            (Mode::Waypoint, Direction::Turn(degrees)) => {
+                let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+                let (wx, wy) = (self.wx, self.wy);
+                self.wx = wx * cos - wy * sin;
+                self.wy = wx * sin + wy * cos;
+            }
+
+            (Mode::Waypoint, Direction::Forward(n)) => {
+                self.x += self.wx * n as f64;
+                self.y += self.wy * n as f64;
             }
         }
+
+        self.record();
     }
 
-    x.abs() + y.abs()
+    pub fn run(mut self, directions: impl Iterator<Item = Direction>) -> Self {
+        directions.for_each(|direction| self.step(direction));
+        self
+    }
 }
 
 #[inline]
-pub fn parse_input() -> impl Iterator<Item = Direction> + Clone {
-    include_str!("input.txt").lines().map(|line| {
+pub fn solve_part1(directions: impl Iterator<Item = Direction>) -> isize {
+    Ship::new(Mode::Heading).run(directions).distance()
+}
+
+#[inline]
+pub fn solve_part2(directions: impl Iterator<Item = Direction>) -> isize {
+    Ship::new(Mode::Waypoint).run(directions).distance()
+}
+
+/// Parse navigation instructions, which may turn by any whole number of degrees
+pub fn parse_directions(input: &str) -> impl Iterator<Item = Direction> + Clone + '_ {
+    input.lines().map(|line| {
         let mut it = line.chars();
         let dir = it.next().unwrap();
         let amount: isize = it.as_str().parse().unwrap();
 
+        let turn = |degrees: isize| {
+            if degrees % 90 == 0 {
+                Direction::Rotate((degrees / 90).rem_euclid(4))
+            } else {
+                Direction::Turn(degrees)
+            }
+        };
+
         match dir {
             'N' => Direction::Move(0, -amount),
             'S' => Direction::Move(0, amount),
             'E' => Direction::Move(amount, 0),
             'W' => Direction::Move(-amount, 0),
 
-            'L' => {
-                debug_assert_eq!(amount % 90, 0);
-                Direction::Rotate(4 - amount / 90)
-            }
-
-            'R' => {
-                debug_assert_eq!(amount % 90, 0);
-                Direction::Rotate(amount / 90)
-            }
+            'L' => turn(-amount),
+            'R' => turn(amount),
 
             'F' => Direction::Forward(amount),
 
@@ -98,6 +197,11 @@ pub fn parse_input() -> impl Iterator<Item = Direction> + Clone {
     })
 }
 
+#[inline]
+pub fn parse_input() -> impl Iterator<Item = Direction> + Clone {
+    parse_directions(include_str!("input.txt"))
+}
+
 #[inline]
 pub fn solve() -> (isize, isize) {
     let directions = parse_input().collect::<Vec<_>>();
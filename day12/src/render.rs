@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use crate::{Mode, Ship};
+
+/// Margin around the route in the SVG output, as a fraction of its size
+const MARGIN: f64 = 0.05;
+
+fn polyline(out: &mut String, points: &[(f64, f64)], style: &str) {
+    out.push_str(r#"<polyline fill="none" vector-effect="non-scaling-stroke" "#);
+    out.push_str(style);
+    out.push_str(r#" points=""#);
+    for &(x, y) in points {
+        write!(out, "{},{} ", x, y).unwrap();
+    }
+    out.pop();
+    out.push_str("\"/>\n");
+}
+
+/// Draw the ship's route, and the waypoint's if it was following one, as SVG
+/// polylines. North is up, like on a map.
+pub fn svg(ship: &Ship) -> String {
+    let waypoint = ship.mode() == Mode::Waypoint;
+
+    let points = || {
+        let waypoints = if waypoint { ship.waypoint_track() } else { &[] };
+        ship.track().iter().chain(waypoints)
+    };
+
+    let min_x = points().map(|&(x, _)| x).fold(f64::INFINITY, f64::min);
+    let max_x = points().map(|&(x, _)| x).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = points().map(|&(_, y)| y).fold(f64::INFINITY, f64::min);
+    let max_y = points().map(|&(_, y)| y).fold(f64::NEG_INFINITY, f64::max);
+
+    let margin = MARGIN * (max_x - min_x).max(max_y - min_y).max(1.0);
+    let (x, y) = (min_x - margin, min_y - margin);
+    let (width, height) = (max_x - min_x + 2.0 * margin, max_y - min_y + 2.0 * margin);
+
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+        x, y, width, height
+    )
+    .unwrap();
+
+    writeln!(
+        out,
+        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="white"/>"#,
+        x, y, width, height
+    )
+    .unwrap();
+
+    if waypoint {
+        polyline(
+            &mut out,
+            ship.waypoint_track(),
+            r#"stroke="orange" stroke-dasharray="4 2""#,
+        );
+    }
+    polyline(&mut out, ship.track(), r#"stroke="blue""#);
+
+    // Mark where the ship set off from and where it ended up
+    let (end_x, end_y) = ship.position();
+    for &(cx, cy, color) in &[(0.0, 0.0, "green"), (end_x, end_y, "red")] {
+        writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="{:.2}" fill="{}"/>"#,
+            cx,
+            cy,
+            margin / 4.0,
+            color
+        )
+        .unwrap();
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// The ship's position after each instruction as CSV, along with the
+/// waypoint's if it was following one
+pub fn csv(ship: &Ship) -> String {
+    let waypoint = ship.mode() == Mode::Waypoint;
+
+    let mut out = String::from("step,x,y");
+    if waypoint {
+        out.push_str(",waypoint_x,waypoint_y");
+    }
+    out.push('\n');
+
+    for (step, (&(x, y), &(wx, wy))) in ship.track().iter().zip(ship.waypoint_track()).enumerate() {
+        write!(out, "{},{},{}", step, x, y).unwrap();
+        if waypoint {
+            write!(out, ",{},{}", wx, wy).unwrap();
+        }
+        out.push('\n');
+    }
+
+    out
+}
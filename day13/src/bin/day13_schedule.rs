@@ -0,0 +1,55 @@
+//! Print the period of the bus pattern, the next valid timestamps after a given
+//! time and the departure table around the first of them, to check part 2 by eye
+//!
+//! Usage: day13_schedule [K] [TIME]
+
+use std::env;
+
+use day13::{parse_input, Schedule};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let k = args
+        .next()
+        .map_or(5, |k| k.parse().expect("K should be a number"));
+    let time = args
+        .next()
+        .map_or(0, |time| time.parse().expect("TIME should be a number"));
+
+    let (_, buses) = parse_input();
+    let schedule = Schedule::new(&buses).expect("no timestamp works for every bus");
+
+    println!("period: {}", schedule.period());
+    println!("valid timestamps after {}:", time);
+    let valid: Vec<_> = schedule.valid_after(time).take(k).collect();
+    for &t in &valid {
+        assert!(schedule.is_valid(t));
+        println!("  {}", t);
+    }
+
+    let start = match valid.first() {
+        Some(&start) => start,
+        None => return,
+    };
+    let span = buses.iter().map(|&(i, _)| i).max().unwrap_or(0) + 1;
+
+    println!();
+    print!("{:<16}", "time");
+    for &(_, bus_id) in &buses {
+        print!("{:<8}", format!("bus {}", bus_id));
+    }
+    println!();
+
+    for (t, departing) in schedule.departures(start..start + span) {
+        print!("{:<16}", t);
+        for &(_, bus_id) in &buses {
+            let mark = if departing.contains(&bus_id) {
+                "D"
+            } else {
+                "."
+            };
+            print!("{:<8}", format!("  {}", mark));
+        }
+        println!();
+    }
+}
@@ -1,4 +1,4 @@
-use ring_algorithm::chinese_remainder_theorem;
+use std::ops::Range;
 
 /// Return the earliest time we can catch a bus after the earliest time we can
 /// catch *any* bus
@@ -27,23 +27,92 @@ where
     bus_id * waiting
 }
 
-/// Solve part 2 by modelling the requirement as a system of modular equations
-/// and solving it by using the CRT
-///
-/// Given a bus id k at offset i, and supposing our solution to the problem is a time t, then
-/// to satisfy the requirement the following relation must hold:
-/// (t + i) mod k = 0
-/// Which can be arranged to the form
-/// t mod k = -i
-/// A system of this kind of equation can be solved by utilizing the aforementioned chinese remainder theorem
+/// The timestamps at which the buses depart at their offsets, which repeat
+/// with a fixed period
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    buses: Vec<(usize, usize)>,
+    first: usize,
+    period: usize,
+}
+
+impl Schedule {
+    /// Model the requirement as a system of modular equations and solve it by
+    /// using the CRT
+    ///
+    /// Given a bus id k at offset i, and supposing our solution to the problem is a time t, then
+    /// to satisfy the requirement the following relation must hold:
+    /// (t + i) mod k = 0
+    /// Which can be arranged to the form
+    /// t mod k = -i
+    /// A system of this kind of equation can be solved by utilizing the aforementioned chinese remainder theorem
+    ///
+    /// Returns `None` if no timestamp satisfies every bus, which can only
+    /// happen if some of the bus ids share a factor
+    pub fn new(buses: &[(usize, usize)]) -> Option<Self> {
+        let (first, period) = numtheory::crt(
+            buses
+                .iter()
+                .map(|&(i, bus_id)| (-(i as i64), bus_id as u64)),
+        )?;
+
+        Some(Self {
+            buses: buses.to_vec(),
+            first: first as usize,
+            period: period as usize,
+        })
+    }
+
+    /// The earliest valid timestamp
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    /// How many minutes apart valid timestamps are
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    /// Does every bus depart at its offset from the given time?
+    pub fn is_valid(&self, time: usize) -> bool {
+        self.buses
+            .iter()
+            .all(|&(i, bus_id)| can_catch_at(time + i, bus_id) == time + i)
+    }
+
+    /// The valid timestamps strictly after the given time, in order
+    pub fn valid_after(&self, time: usize) -> impl Iterator<Item = usize> {
+        let start = if time < self.first {
+            self.first
+        } else {
+            self.first + ((time - self.first) / self.period + 1) * self.period
+        };
+        let period = self.period;
+
+        (0..).map(move |k| start + k * period)
+    }
+
+    /// Every minute in the window along with the ids of the buses that depart
+    /// then, like the table in the puzzle's examples
+    pub fn departures(&self, window: Range<usize>) -> Vec<(usize, Vec<usize>)> {
+        window
+            .map(|time| {
+                let buses = self
+                    .buses
+                    .iter()
+                    .map(|&(_, bus_id)| bus_id)
+                    .filter(|&bus_id| can_catch_at(time, bus_id) == time)
+                    .collect();
+                (time, buses)
+            })
+            .collect()
+    }
+}
+
+/// Solve part 2 by finding the earliest timestamp of the schedule
 #[inline]
 pub fn solve_part2(buses: &[(usize, usize)]) -> usize {
-    let (rhs, moduli): (Vec<_>, Vec<_>) = buses
-        .iter()
-        .map(|&(i, bus_id)| (-(i as isize), bus_id as isize))
-        .unzip();
-
-    chinese_remainder_theorem(&rhs, &moduli).unwrap() as usize
+    Schedule::new(buses).unwrap().first()
 }
 
 #[inline]
@@ -0,0 +1,46 @@
+//! Print the contents of memory after running the program with each version of
+//! the decoder chip, along with the instruction that last wrote each value
+//!
+//! Usage: day14_dump [1|2]
+//!
+//! Addresses written with floating bits are shown as a pattern instead.
+
+use std::env;
+
+use day14::{parse_input, run, Version};
+
+fn main() {
+    let versions = match env::args().nth(1).as_deref() {
+        None => vec![Version::Value, Version::Address],
+        Some("1") => vec![Version::Value],
+        Some("2") => vec![Version::Address],
+        Some(other) => panic!("unknown part {:?}, expected 1 or 2", other),
+    };
+
+    let instructions = parse_input();
+
+    for version in versions {
+        let memory = run(&instructions, version);
+
+        let mut regions = memory.regions().to_vec();
+        regions.sort_unstable_by_key(|r| r.pattern.address());
+
+        println!("== {:?} decoder ==", version);
+        for r in regions {
+            let address = if r.pattern.count() == 1 {
+                r.pattern.address().to_string()
+            } else {
+                r.pattern.to_string()
+            };
+
+            println!(
+                "{:>36} = {:<12} line {:>3}: {}",
+                address,
+                r.value,
+                r.writer + 1,
+                instructions[r.writer]
+            );
+        }
+        println!("sum: {}\n", memory.sum());
+    }
+}
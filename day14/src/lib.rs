@@ -1,4 +1,7 @@
-use rustc_hash::FxHashMap as HashMap;
+use std::fmt::{self, Display};
+
+pub mod memory;
+use memory::{Memory, Pattern};
 
 const MASK_HEADER: &str = "mask = ";
 const SET_HEADER: &str = "mem[";
@@ -11,45 +14,48 @@ pub enum Instruction {
     Set { address: usize, value: u64 },
 }
 
-pub fn solve_part1(instructions: &[Instruction]) -> u64 {
-    let mut mem = [0u64; 1 << 16];
-
-    let mut mask = 0;
-    let mut metamask = 0;
-    for &instruction in instructions {
-        match instruction {
-            Instruction::Mask {
-                mask: next_mask,
-                metamask: next_metamask,
-            } => {
-                mask = next_mask;
-                metamask = next_metamask;
+/// Written the same way as in the input
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Mask { mask, metamask } => {
+                f.write_str(MASK_HEADER)?;
+                (0..INT_SIZE).rev().try_for_each(|i| {
+                    let ch = if metamask & (1 << i) == 0 {
+                        'X'
+                    } else if mask & (1 << i) != 0 {
+                        '1'
+                    } else {
+                        '0'
+                    };
+                    write!(f, "{}", ch)
+                })
             }
 
             Instruction::Set { address, value } => {
-                mem[address] = (value & !metamask) | mask;
+                write!(f, "{}{}] = {}", SET_HEADER, address, value)
             }
         }
     }
-
-    mem.iter().sum()
 }
 
-fn set(memory: &mut HashMap<u64, u64>, metamask: u64, address: u64, value: u64) {
-    let floaty = !metamask & ((1 << INT_SIZE) - 1);
-    let mut xor = 1 + floaty;
-    (0..1u64 << floaty.count_ones()).for_each(|_| {
-        xor = (xor - 1) & floaty;
-        memory.insert(address ^ xor, value);
-    });
+/// How the bitmask is applied when writing to memory
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Version {
+    /// The mask overwrites bits of the value
+    Value,
+    /// The mask sets bits of the address, and its `X`s make the write cover
+    /// every possible address
+    Address,
 }
 
-pub fn solve_part2(instructions: &[Instruction]) -> u64 {
-    let mut memory = HashMap::with_capacity_and_hasher(1 << 16, Default::default());
+/// Run the program with the given version of the decoder chip
+pub fn run(instructions: &[Instruction], version: Version) -> Memory {
+    let mut memory = Memory::new();
 
     let mut mask = 0;
     let mut metamask = 0;
-    for &instruction in instructions {
+    for (i, &instruction) in instructions.iter().enumerate() {
         match instruction {
             Instruction::Mask {
                 mask: next_mask,
@@ -59,14 +65,29 @@ pub fn solve_part2(instructions: &[Instruction]) -> u64 {
                 metamask = next_metamask;
             }
 
-            Instruction::Set { address, value } => {
-                let address = address as u64 | mask;
-                set(&mut memory, metamask, address, value);
-            }
+            Instruction::Set { address, value } => match version {
+                Version::Value => {
+                    let value = (value & !metamask) | mask;
+                    memory.write(Pattern::new(address as u64, 0), value, i);
+                }
+
+                Version::Address => {
+                    let floating = !metamask & ((1 << INT_SIZE) - 1);
+                    memory.write(Pattern::new(address as u64 | mask, floating), value, i);
+                }
+            },
         }
     }
 
-    memory.values().sum()
+    memory
+}
+
+pub fn solve_part1(instructions: &[Instruction]) -> u64 {
+    run(instructions, Version::Value).sum()
+}
+
+pub fn solve_part2(instructions: &[Instruction]) -> u64 {
+    run(instructions, Version::Address).sum()
 }
 
 pub fn parse_input() -> Vec<Instruction> {
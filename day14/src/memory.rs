@@ -0,0 +1,165 @@
+//! Memory that stores each write as the pattern of addresses it covers, so that
+//! a write to millions of floating addresses costs as much as a write to one.
+//!
+//! The regions in memory never overlap: a new write carves its addresses out of
+//! every region it covers before being added, which is what lets us add up the
+//! whole memory by looking at each region once.
+
+use std::fmt::{self, Display};
+
+use crate::INT_SIZE;
+
+/// A set of addresses, where the bits in `floating` can be either 0 or 1 and
+/// the others must match `address`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Pattern {
+    /// Always has the floating bits cleared
+    address: u64,
+    floating: u64,
+}
+
+impl Pattern {
+    pub fn new(address: u64, floating: u64) -> Self {
+        Self {
+            address: address & !floating,
+            floating,
+        }
+    }
+
+    /// The lowest address in the pattern
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    pub fn floating(&self) -> u64 {
+        self.floating
+    }
+
+    /// How many addresses are in the pattern
+    pub fn count(&self) -> u64 {
+        1 << self.floating.count_ones()
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        address & !self.floating == self.address
+    }
+
+    /// Do the two patterns have any address in common? They do unless they
+    /// disagree on a bit that's fixed in both.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        (self.address ^ other.address) & !self.floating & !other.floating == 0
+    }
+
+    /// Split the addresses in `self` but not in `other` into disjoint patterns
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+
+        // Go through the bits that float here but are fixed in `other`: the
+        // addresses that disagree with `other` on that bit are a piece of the
+        // result, and we carry on with the ones that agree
+        let mut pieces = Vec::new();
+        let mut rest = *self;
+        let mut split = self.floating & !other.floating;
+
+        while split != 0 {
+            let bit = split & split.wrapping_neg();
+            split &= !bit;
+
+            rest.floating &= !bit;
+            pieces.push(Self {
+                address: rest.address | (!other.address & bit),
+                floating: rest.floating,
+            });
+            rest.address |= other.address & bit;
+        }
+
+        // What's left is entirely inside `other`
+        pieces
+    }
+}
+
+/// Written like a mask: the address in binary, with an `X` for each floating bit
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (0..INT_SIZE).rev().try_for_each(|i| {
+            let ch = if self.floating & (1 << i) != 0 {
+                'X'
+            } else if self.address & (1 << i) != 0 {
+                '1'
+            } else {
+                '0'
+            };
+            write!(f, "{}", ch)
+        })
+    }
+}
+
+/// A pattern of addresses that all hold the same value
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Region {
+    pub pattern: Pattern,
+    pub value: u64,
+    /// The index of the instruction that wrote the value
+    pub writer: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    regions: Vec<Region>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the value to every address in the pattern, on behalf of the
+    /// instruction with the given index
+    pub fn write(&mut self, pattern: Pattern, value: u64, writer: usize) {
+        if self.regions.iter().any(|r| r.pattern.overlaps(&pattern)) {
+            self.regions = self
+                .regions
+                .iter()
+                .flat_map(|r| {
+                    r.pattern
+                        .subtract(&pattern)
+                        .into_iter()
+                        .map(move |piece| Region {
+                            pattern: piece,
+                            ..*r
+                        })
+                })
+                .collect();
+        }
+
+        self.regions.push(Region {
+            pattern,
+            value,
+            writer,
+        });
+    }
+
+    /// The value at the given address
+    pub fn read(&self, address: u64) -> u64 {
+        self.regions
+            .iter()
+            .find(|r| r.pattern.contains(address))
+            .map_or(0, |r| r.value)
+    }
+
+    /// The disjoint regions of memory that have been written to, in the order
+    /// they were written
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// The sum of every value in memory
+    pub fn sum(&self) -> u64 {
+        self.regions
+            .iter()
+            .map(|r| r.pattern.count() * r.value)
+            .sum()
+    }
+}
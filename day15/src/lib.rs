@@ -1,36 +1,161 @@
-use std::mem::replace;
-
 const IDX_PART1: usize = 2020;
 const IDX_PART2: usize = 30_000_000;
-const SEED_LEN: usize = 6;
 
-#[inline]
-pub fn solve() -> (usize, usize) {
-    let mut prev = 0;
+/// Numbers below this get a slot in a small table that stays in cache. Most
+/// numbers spoken are small, so this is where most of the lookups land.
+const LOW_LEN: usize = 1 << 16;
+
+/// The turn each number was last spoken on, split into two tiers.
+///
+/// The numbers from `LOW_LEN` up get a flat table as long as the game. It's
+/// allocated zeroed, so its pages are only mapped in as we first write to
+/// them, but the numbers are spread out enough that a full game still ends up
+/// touching nearly all of it.
+///
+/// Most of those numbers are only ever spoken once, so we also keep a bitset
+/// of which ones have been spoken, which is small enough to mostly stay in
+/// cache. It tells us a number is new without waiting on a cold read from the
+/// table, and the write that follows doesn't hold up the next turn. On the
+/// full game that's almost twice as fast as going straight to the table, and a
+/// hash map in its place was slower still.
+struct LastSeen {
+    /// Turns for the low numbers, 0 if never spoken
+    low: Vec<u32>,
+    /// Turns for the high numbers, only meaningful if their bit is set in `seen`
+    high: Vec<u32>,
+    seen: Vec<u64>,
+}
+
+impl LastSeen {
+    fn new(size: usize) -> Self {
+        let high_len = size.saturating_sub(LOW_LEN);
+
+        Self {
+            low: vec![0; LOW_LEN.min(size)],
+            high: vec![0; high_len],
+            seen: vec![0; high_len / 64 + 1],
+        }
+    }
+
+    /// Record that `n` was spoken on `turn`, returning the turn it was last
+    /// spoken on before that, if any
+    #[inline]
+    fn replace(&mut self, n: usize, turn: u32) -> Option<u32> {
+        if n < LOW_LEN {
+            match std::mem::replace(&mut self.low[n], turn) {
+                0 => None,
+                lturn => Some(lturn),
+            }
+        } else {
+            let n = n - LOW_LEN;
+            let (word, bit) = (n / 64, 1 << (n % 64));
+
+            if self.seen[word] & bit == 0 {
+                self.seen[word] |= bit;
+                self.high[n] = turn;
+                None
+            } else {
+                Some(std::mem::replace(&mut self.high[n], turn))
+            }
+        }
+    }
+}
+
+/// The elves' memory game, yielding the number spoken on each turn starting
+/// from turn 1 and stopping after a given number of turns
+pub struct MemoryGame {
+    seed: Vec<usize>,
+    /// The turn each number was last spoken on, not counting the latest turn
+    last_seen: LastSeen,
+    /// How many turns have been played so far
+    turn: usize,
+    /// The number spoken on the latest turn
+    prev: usize,
+    limit: usize,
+}
+
+impl MemoryGame {
+    pub fn new(seed: &[usize], limit: usize) -> Self {
+        assert!(
+            !seed.is_empty(),
+            "the game needs at least one starting number"
+        );
+        assert!(
+            limit <= u32::MAX as usize,
+            "the game can't go on for more than {} turns",
+            u32::MAX
+        );
+
+        // After the starting numbers, the number spoken on a turn is always less
+        // than the turn itself
+        let size = seed.iter().map(|&n| n + 1).max().unwrap().max(limit);
+
+        Self {
+            seed: seed.to_vec(),
+            last_seen: LastSeen::new(size),
+            turn: 0,
+            prev: 0,
+            limit,
+        }
+    }
+
+    /// The number spoken on the given turn, counting from 1, which must be
+    /// after the latest turn played and within the limit
+    pub fn nth_turn(&mut self, turn: usize) -> usize {
+        assert!(turn > self.turn, "turn {} has already been played", turn);
+        assert!(turn <= self.limit, "turn {} is past the limit", turn);
+        self.nth(turn - self.turn - 1).unwrap()
+    }
+}
+
+impl Iterator for MemoryGame {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.turn == self.limit {
+            return None;
+        }
+
+        self.turn += 1;
+        let turn = self.turn;
+
+        let next = if turn == 1 {
+            self.seed[0]
+        } else {
+            let next = match self.last_seen.replace(self.prev, turn as u32 - 1) {
+                None => 0,
+                Some(lturn) => turn - 1 - lturn as usize,
+            };
+
+            self.seed.get(turn - 1).copied().unwrap_or(next)
+        };
+
+        self.prev = next;
+        Some(next)
+    }
 
-    let mut last_seen = vec![0; IDX_PART2];
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.limit - self.turn;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MemoryGame {}
 
+pub fn parse_input() -> Vec<usize> {
     include_str!("input.txt")
         .trim()
         .split(',')
         .map(|n| n.parse::<usize>().unwrap())
-        .enumerate()
-        .inspect(|(_idx, n)| prev = *n)
-        .for_each(|(idx, n)| last_seen[n] = idx + 1);
-
-    let mut sequence = (SEED_LEN..).map(|turn| {
-        let next = match last_seen[prev] {
-            0 => 0,
-            lturn => turn - lturn,
-        };
-        last_seen[prev] = turn;
-        replace(&mut prev, next)
-    });
-
-    let part1 = sequence.nth(IDX_PART1 - SEED_LEN).unwrap();
-    let part2 = sequence
-        .nth(IDX_PART2 - (IDX_PART1 - SEED_LEN) - (SEED_LEN + 1))
-        .unwrap();
+        .collect()
+}
+
+#[inline]
+pub fn solve() -> (usize, usize) {
+    let mut game = MemoryGame::new(&parse_input(), IDX_PART2);
+
+    let part1 = game.nth_turn(IDX_PART1);
+    let part2 = game.nth_turn(IDX_PART2);
 
     (part1, part2)
 }
@@ -0,0 +1,28 @@
+//! Print how each expression is grouped under the part 1 and part 2 precedences
+
+use std::fmt::Display;
+
+use day18::{EvalError, Expr, Precedence};
+
+#[cfg(feature = "bigint")]
+type Num = num_bigint::BigUint;
+#[cfg(not(feature = "bigint"))]
+type Num = u64;
+
+fn show(result: Result<impl Display, EvalError>) -> String {
+    match result {
+        Ok(n) => n.to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn main() {
+    for line in include_str!("../input.txt").lines() {
+        let part1 = Expr::parse(line, &Precedence::PART1);
+        let part2 = Expr::parse(line, &Precedence::PART2);
+
+        println!("{}", line);
+        println!("  part 1: {} = {}", part1, show(part1.eval::<Num>()));
+        println!("  part 2: {} = {}", part2, show(part2.eval::<Num>()));
+    }
+}
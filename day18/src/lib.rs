@@ -1,156 +1,287 @@
-enum Op {
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
     Add,
+    Sub,
     Mul,
+    Div,
 }
 
-struct SimpleParser {
-    line: &'static [u8],
+impl Op {
+    fn from_byte(ch: u8) -> Option<Self> {
+        match ch {
+            b'+' => Some(Op::Add),
+            b'-' => Some(Op::Sub),
+            b'*' => Some(Op::Mul),
+            b'/' => Some(Op::Div),
+            _ => None,
+        }
+    }
 }
 
-impl SimpleParser {
-    fn new(line: &'static str) -> Self {
-        Self {
-            line: line.as_bytes(),
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+        })
+    }
+}
+
+/// Binding power of each operator: higher binds tighter, and operators that
+/// share a binding power associate to the left. Any `u8` works, from 0 to 255.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Precedence {
+    pub add: u8,
+    pub sub: u8,
+    pub mul: u8,
+    pub div: u8,
+}
+
+impl Precedence {
+    /// Every operator has the same precedence
+    pub const PART1: Self = Self {
+        add: 1,
+        sub: 1,
+        mul: 1,
+        div: 1,
+    };
+
+    /// Addition and subtraction are evaluated before multiplication and division
+    pub const PART2: Self = Self {
+        add: 2,
+        sub: 2,
+        mul: 1,
+        div: 1,
+    };
+
+    fn of(&self, op: Op) -> u8 {
+        match op {
+            Op::Add => self.add,
+            Op::Sub => self.sub,
+            Op::Mul => self.mul,
+            Op::Div => self.div,
         }
     }
+}
 
-    fn skip(&mut self, n: usize) {
-        self.line = &self.line[n.min(self.line.len())..];
+/// A number type expressions can be evaluated in
+pub trait Value: Sized {
+    /// Build a number out of its decimal digits, returning `None` if it can't
+    /// be represented
+    fn from_digits(digits: &str) -> Option<Self>;
+
+    fn is_zero(&self) -> bool;
+
+    /// Apply the operator, returning `None` if the result can't be represented
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Option<Self>;
+}
+
+impl Value for u64 {
+    fn from_digits(digits: &str) -> Option<Self> {
+        digits.parse().ok()
     }
 
-    fn num(&mut self) -> u64 {
-        let mut n = 0;
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
 
-        loop {
-            let ch = match self.line.first() {
-                Some(&ch) => ch,
-                None => break n,
-            };
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Option<Self> {
+        match op {
+            Op::Add => lhs.checked_add(rhs),
+            Op::Sub => lhs.checked_sub(rhs),
+            Op::Mul => lhs.checked_mul(rhs),
+            Op::Div => lhs.checked_div(rhs),
+        }
+    }
+}
 
-            if (b'0'..=b'9').contains(&ch) {
-                n = 10 * n + u64::from(ch - b'0');
-                self.skip(1);
-            } else {
-                break n;
-            }
+#[cfg(feature = "bigint")]
+impl Value for num_bigint::BigUint {
+    fn from_digits(digits: &str) -> Option<Self> {
+        Self::parse_bytes(digits.as_bytes(), 10)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Option<Self> {
+        match op {
+            Op::Add => Some(lhs + rhs),
+            Op::Sub if lhs < rhs => None,
+            Op::Sub => Some(lhs - rhs),
+            Op::Mul => Some(lhs * rhs),
+            Op::Div if rhs.is_zero() => None,
+            Op::Div => Some(lhs / rhs),
         }
     }
+}
 
-    fn atom(&mut self) -> u64 {
-        if self.line[0] == b'(' {
-            self.skip(1);
-            self.expr()
-        } else {
-            self.num()
+/// Why evaluating an expression failed, along with the byte offset of the
+/// offending operator or number in the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalError {
+    Overflow(usize),
+    DivisionByZero(usize),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Overflow(pos) => write!(f, "overflow at byte {}", pos),
+            EvalError::DivisionByZero(pos) => write!(f, "division by zero at byte {}", pos),
         }
     }
+}
 
-    fn op(&mut self) -> Op {
-        self.skip(1);
-        let op = match self.line[0] {
-            b'+' => Op::Add,
-            b'*' => Op::Mul,
-            _ => unreachable!(),
+impl Error for EvalError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A number, kept as written so that it can be as big as the type it's
+    /// evaluated in allows
+    Num {
+        digits: String,
+        /// Byte offset of the number in the line
+        pos: usize,
+    },
+    BinOp {
+        op: Op,
+        /// Byte offset of the operator in the line
+        pos: usize,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+impl Expr {
+    pub fn parse(line: &str, precedence: &Precedence) -> Self {
+        let mut parser = Parser {
+            line: line.as_bytes(),
+            len: line.len(),
+            precedence,
         };
-        self.skip(2);
-        op
+
+        let expr = parser.expr(0);
+        parser.skip_whitespace();
+        assert!(parser.line.is_empty(), "trailing input {:?}", parser.line);
+        expr
     }
 
-    fn expr(&mut self) -> u64 {
-        let mut acc = self.atom();
+    pub fn eval<V: Value>(&self) -> Result<V, EvalError> {
+        match self {
+            Expr::Num { digits, pos } => V::from_digits(digits).ok_or(EvalError::Overflow(*pos)),
 
-        loop {
-            if self.line.is_empty() || self.line[0] == b')' {
-                self.skip(1);
-                break acc;
-            }
+            Expr::BinOp { op, pos, lhs, rhs } => {
+                let lhs = lhs.eval::<V>()?;
+                let rhs = rhs.eval::<V>()?;
 
-            let op = self.op();
-            let rhs = self.atom();
+                if *op == Op::Div && rhs.is_zero() {
+                    return Err(EvalError::DivisionByZero(*pos));
+                }
 
-            match op {
-                Op::Add => acc += rhs,
-                Op::Mul => acc *= rhs,
+                V::apply(*op, lhs, rhs).ok_or(EvalError::Overflow(*pos))
             }
         }
     }
 }
 
-struct AdvancedParser {
-    line: &'static [u8],
-}
-
-impl AdvancedParser {
-    fn new(line: &'static str) -> Self {
-        Self {
-            line: line.as_bytes(),
+/// Print the expression with every operation wrapped in parentheses, making
+/// the grouping chosen by the precedence explicit
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num { digits, .. } => f.write_str(digits),
+            Expr::BinOp { op, lhs, rhs, .. } => write!(f, "({} {} {})", lhs, op, rhs),
         }
     }
+}
 
+/// Pratt parser building an `Expr` out of a single line
+struct Parser<'a> {
+    line: &'a [u8],
+    len: usize,
+    precedence: &'a Precedence,
+}
+
+impl Parser<'_> {
     fn skip(&mut self, n: usize) {
         self.line = &self.line[n.min(self.line.len())..];
     }
 
-    fn num(&mut self) -> u64 {
-        let mut n = 0;
-
-        loop {
-            let ch = match self.line.first() {
-                Some(&ch) => ch,
-                None => break n,
-            };
+    fn pos(&self) -> usize {
+        self.len - self.line.len()
+    }
 
-            if (b'0'..=b'9').contains(&ch) {
-                n = 10 * n + u64::from(ch - b'0');
-                self.skip(1);
-            } else {
-                break n;
-            }
+    fn skip_whitespace(&mut self) {
+        while self.line.first() == Some(&b' ') {
+            self.skip(1);
         }
     }
 
-    fn atom(&mut self) -> u64 {
+    fn num(&mut self) -> Expr {
+        let pos = self.pos();
+        let len = self
+            .line
+            .iter()
+            .take_while(|ch| ch.is_ascii_digit())
+            .count();
+        assert!(len > 0, "expected a number at byte {}", pos);
+
+        let digits = String::from_utf8(self.line[..len].to_vec()).unwrap();
+        self.skip(len);
+        Expr::Num { digits, pos }
+    }
+
+    fn atom(&mut self) -> Expr {
+        self.skip_whitespace();
+
         if self.line[0] == b'(' {
             self.skip(1);
-            self.expr(true)
+            let expr = self.expr(0);
+            self.skip_whitespace();
+            assert_eq!(self.line.first(), Some(&b')'), "unbalanced parentheses");
+            self.skip(1);
+            expr
         } else {
             self.num()
         }
     }
 
-    fn op(&mut self) -> Op {
-        self.skip(1);
-        let op = match self.line[0] {
-            b'+' => Op::Add,
-            b'*' => Op::Mul,
-            _ => unreachable!(),
-        };
-        self.skip(2);
-        op
-    }
-
-    fn expr(&mut self, eat: bool) -> u64 {
-        let mut acc = self.atom();
+    /// Parse an expression containing only operators that bind at least as
+    /// tightly as `min_bp`. It's wider than a binding power so that there's
+    /// room for one above the highest.
+    fn expr(&mut self, min_bp: u16) -> Expr {
+        let mut lhs = self.atom();
 
         loop {
-            if self.line.is_empty() || self.line[0] == b')' {
-                if eat {
-                    self.skip(1);
-                }
-                break acc;
-            }
-
-            let op = self.op();
+            self.skip_whitespace();
 
-            match op {
-                Op::Add => {
-                    acc += self.atom();
-                }
+            // Either the end of the line or a closing parenthesis
+            let op = match self.line.first().and_then(|&ch| Op::from_byte(ch)) {
+                Some(op) => op,
+                None => break lhs,
+            };
 
-                Op::Mul => {
-                    acc *= self.expr(false);
-                }
+            let bp = u16::from(self.precedence.of(op));
+            if bp < min_bp {
+                break lhs;
             }
+            let pos = self.pos();
+            self.skip(1);
+
+            // Requiring a strictly higher binding power on the right makes us left-associative
+            let rhs = self.expr(bp + 1);
+            lhs = Expr::BinOp {
+                op,
+                pos,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
         }
     }
 }
@@ -161,8 +292,8 @@ pub fn solve() -> (u64, u64) {
     let mut part2 = 0;
 
     for line in include_str!("input.txt").lines() {
-        part1 += SimpleParser::new(line).expr();
-        part2 += AdvancedParser::new(line).expr(true);
+        part1 += Expr::parse(line, &Precedence::PART1).eval::<u64>().unwrap();
+        part2 += Expr::parse(line, &Precedence::PART2).eval::<u64>().unwrap();
     }
 
     (part1, part2)
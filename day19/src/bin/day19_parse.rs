@@ -0,0 +1,34 @@
+//! Print the parse tree of every message matching rule 0, and explain why
+//! every other message was rejected
+//!
+//! Usage: day19_parse [FILE]
+//!
+//! FILE has the same format as the puzzle input, which is used by default.
+
+use std::{env, fs};
+
+use day19::{earley, parse_puzzle};
+
+fn main() {
+    let input = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => include_str!("../input.txt").to_owned(),
+    };
+
+    let (grammar, messages) = parse_puzzle(&input);
+    let start = grammar.index("0").expect("there should be a rule 0");
+
+    for message in messages {
+        let chart = earley::Chart::new(&grammar, start, message.as_bytes());
+
+        println!("{}", message);
+        match chart.failure() {
+            None => print!(
+                "{}",
+                chart.tree().unwrap().display(&grammar, message.as_bytes())
+            ),
+            Some(failure) => print!("{}", failure.display(&grammar, message.as_bytes())),
+        }
+        println!();
+    }
+}
@@ -0,0 +1,161 @@
+//! Fast path for grammars shaped like the puzzle's, where rule 0 boils down to
+//! some chunks matching rule 42 followed by some chunks matching rule 31.
+//!
+//! Every message is really a bit string where "a" is 0 and "b" is 1, and the
+//! chunks all have the same length, so we can precompute which chunks match
+//! each rule and then check a message one table lookup per chunk.
+
+use crate::{Grammar, Symbol};
+
+/// The longest chunk we're willing to build a table for
+const MAX_CHUNK_LEN: usize = 16;
+
+/// A message packed into an integer, first byte in the most significant bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    bits: u128,
+    len: usize,
+}
+
+impl Message {
+    /// Pack a message made up of only "a" and "b" and at most 128 bytes long
+    pub fn pack(line: &[u8]) -> Option<Self> {
+        if line.len() > 128 {
+            return None;
+        }
+
+        let bits = line.iter().try_fold(0u128, |acc, &ch| match ch {
+            b'a' => Some(acc << 1),
+            b'b' => Some((acc << 1) | 1),
+            _ => None,
+        })?;
+
+        Some(Self {
+            bits,
+            len: line.len(),
+        })
+    }
+}
+
+/// Lookup tables of every chunk matching rules 42 and 31
+pub struct ChunkTables {
+    chunk_len: usize,
+    rule42: Vec<bool>,
+    rule31: Vec<bool>,
+}
+
+/// The language of a rule that only matches strings of a given length, as the
+/// packed strings it matches. Returns `None` if the rule can match strings of
+/// different lengths, loops, or uses terminals other than "a" and "b".
+fn language(grammar: &Grammar, rule: usize, depth: usize) -> Option<(usize, Vec<u32>)> {
+    // A fixed-length rule can't nest deeper than there are rules
+    if depth > grammar.len() {
+        return None;
+    }
+
+    let mut len = None;
+    let mut words = Vec::new();
+
+    for alt in grammar.alternatives(rule) {
+        let mut alt_len = 0;
+        let mut alt_words = vec![0u32];
+
+        for sym in alt {
+            let (sym_len, sym_words) = match sym {
+                Symbol::Rule(sub) => language(grammar, *sub, depth + 1)?,
+                Symbol::Terminal(t) => (t.len(), vec![Message::pack(t.as_bytes())?.bits as u32]),
+            };
+
+            alt_len += sym_len;
+            if alt_len > MAX_CHUNK_LEN {
+                return None;
+            }
+
+            alt_words = alt_words
+                .iter()
+                .flat_map(|&prefix| sym_words.iter().map(move |&w| (prefix << sym_len) | w))
+                .collect();
+        }
+
+        if *len.get_or_insert(alt_len) != alt_len {
+            return None;
+        }
+        words.extend(alt_words);
+    }
+
+    Some((len?, words))
+}
+
+impl ChunkTables {
+    /// Build the tables, if the grammar has the expected shape:
+    /// `0: 8 11`, `8: 42` and `11: 42 31`, with 42 and 31 matching disjoint
+    /// sets of chunks of the same length.
+    pub fn new(grammar: &Grammar) -> Option<Self> {
+        let rule = |name| grammar.index(name);
+        let (r0, r8, r11) = (rule("0")?, rule("8")?, rule("11")?);
+        let (r42, r31) = (rule("42")?, rule("31")?);
+
+        let has_shape = |r: usize, seq: &[usize]| {
+            grammar.alternatives(r)
+                == [seq.iter().map(|&sub| Symbol::Rule(sub)).collect::<Vec<_>>()]
+        };
+
+        if !has_shape(r0, &[r8, r11]) || !has_shape(r8, &[r42]) || !has_shape(r11, &[r42, r31]) {
+            return None;
+        }
+
+        let table = |r: usize| {
+            let (len, words) = language(grammar, r, 0)?;
+            let mut table = vec![false; 1 << len];
+            words.into_iter().for_each(|w| table[w as usize] = true);
+            Some((len, table))
+        };
+
+        let (len42, rule42) = table(r42)?;
+        let (len31, rule31) = table(r31)?;
+
+        if len42 != len31 || len42 == 0 || rule42.iter().zip(&rule31).any(|(&a, &b)| a && b) {
+            return None;
+        }
+
+        Some(Self {
+            chunk_len: len42,
+            rule42,
+            rule31,
+        })
+    }
+
+    /// Count how many chunks at the start of the message match rule 42 and how
+    /// many of the remaining ones match rule 31, or `None` if some chunk
+    /// matches neither
+    pub fn count_chunks(&self, msg: Message) -> Option<(usize, usize)> {
+        let chunks = msg.len / self.chunk_len;
+        if chunks * self.chunk_len != msg.len {
+            return None;
+        }
+
+        let mask = (1 << self.chunk_len) - 1;
+        let chunk = |k: usize| ((msg.bits >> (msg.len - (k + 1) * self.chunk_len)) & mask) as usize;
+
+        let left = (0..chunks).take_while(|&k| self.rule42[chunk(k)]).count();
+        let right = (left..chunks)
+            .take_while(|&k| self.rule31[chunk(k)])
+            .count();
+
+        if left + right == chunks {
+            Some((left, right))
+        } else {
+            None
+        }
+    }
+
+    /// Does the message match rule 0 as given?
+    pub fn matches_part1(&self, msg: Message) -> bool {
+        self.count_chunks(msg) == Some((2, 1))
+    }
+
+    /// Does the message match rule 0 with the looping versions of rules 8 and 11?
+    pub fn matches_part2(&self, msg: Message) -> bool {
+        matches!(self.count_chunks(msg), Some((left, right)) if right >= 1 && left > right)
+    }
+}
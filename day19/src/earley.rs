@@ -0,0 +1,379 @@
+//! Earley parser, which handles any context-free grammar including left
+//! recursion and empty rules
+
+use std::{collections::HashSet, fmt, ops::Range};
+
+use crate::{Grammar, Symbol};
+
+/// A partially matched alternative: `dot` symbols of alternative `alt` of
+/// `rule` have matched the input starting at offset `origin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+impl Item {
+    fn advance(self) -> Self {
+        Self {
+            dot: self.dot + 1,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree {
+    Rule {
+        rule: usize,
+        alternative: usize,
+        span: Range<usize>,
+        children: Vec<ParseTree>,
+    },
+    Terminal(Range<usize>),
+}
+
+impl ParseTree {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            ParseTree::Rule { span, .. } => span.clone(),
+            ParseTree::Terminal(span) => span.clone(),
+        }
+    }
+
+    /// Display the tree one node per line, indented by depth, with the rule
+    /// names taken from the grammar and the matched text from the input
+    pub fn display<'a>(&'a self, grammar: &'a Grammar, input: &'a [u8]) -> impl fmt::Display + 'a {
+        TreeDisplay {
+            tree: self,
+            grammar,
+            input,
+        }
+    }
+}
+
+struct TreeDisplay<'a> {
+    tree: &'a ParseTree,
+    grammar: &'a Grammar,
+    input: &'a [u8],
+}
+
+impl TreeDisplay<'_> {
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>, node: &ParseTree, depth: usize) -> fmt::Result {
+        let text = String::from_utf8_lossy(&self.input[node.span()]);
+
+        match node {
+            ParseTree::Rule { rule, children, .. } => {
+                writeln!(
+                    f,
+                    "{:indent$}{} {:?}",
+                    "",
+                    self.grammar.name(*rule),
+                    text,
+                    indent = 2 * depth
+                )?;
+                children
+                    .iter()
+                    .try_for_each(|child| self.fmt_node(f, child, depth + 1))
+            }
+
+            ParseTree::Terminal(_) => writeln!(f, "{:indent$}{:?}", "", text, indent = 2 * depth),
+        }
+    }
+}
+
+impl fmt::Display for TreeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_node(f, self.tree, 0)
+    }
+}
+
+/// Why an input was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// Length of the longest prefix of the input that can still be extended
+    /// into a match, which is also the offset at which matching failed
+    pub offset: usize,
+    /// The rules that were trying to match a terminal at that offset, along
+    /// with the terminal they expected
+    pub expected: Vec<(usize, String)>,
+}
+
+impl Failure {
+    pub fn display<'a>(&'a self, grammar: &'a Grammar, input: &'a [u8]) -> impl fmt::Display + 'a {
+        FailureDisplay {
+            failure: self,
+            grammar,
+            input,
+        }
+    }
+}
+
+struct FailureDisplay<'a> {
+    failure: &'a Failure,
+    grammar: &'a Grammar,
+    input: &'a [u8],
+}
+
+impl fmt::Display for FailureDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = self.failure.offset;
+        let prefix = String::from_utf8_lossy(&self.input[..offset]);
+
+        match self.input.get(offset) {
+            Some(&ch) => writeln!(
+                f,
+                "matched {:?}, then failed at byte {} ({:?})",
+                prefix, offset, ch as char
+            )?,
+            None => writeln!(f, "matched {:?}, then ran out of input", prefix)?,
+        }
+
+        // Nothing left to match means the start rule was done before the input was
+        if self.failure.expected.is_empty() {
+            writeln!(f, "  expected end of input")?;
+        }
+
+        self.failure
+            .expected
+            .iter()
+            .try_for_each(|(rule, terminal)| {
+                writeln!(
+                    f,
+                    "  rule {} expected {:?}",
+                    self.grammar.name(*rule),
+                    terminal
+                )
+            })
+    }
+}
+
+/// The Earley sets for an input, where the set at offset `i` holds every item
+/// that has matched the input up to `i`
+pub struct Chart<'a> {
+    grammar: &'a Grammar,
+    input: &'a [u8],
+    start: usize,
+    sets: Vec<Vec<Item>>,
+    seen: Vec<HashSet<Item>>,
+}
+
+impl<'a> Chart<'a> {
+    pub fn new(grammar: &'a Grammar, start: usize, input: &'a [u8]) -> Self {
+        let mut chart = Self {
+            grammar,
+            input,
+            start,
+            sets: vec![Vec::new(); input.len() + 1],
+            seen: vec![HashSet::new(); input.len() + 1],
+        };
+
+        let nullable = grammar.nullable();
+
+        for alt in 0..grammar.alternatives(start).len() {
+            chart.add(
+                0,
+                Item {
+                    rule: start,
+                    alt,
+                    dot: 0,
+                    origin: 0,
+                },
+            );
+        }
+
+        for i in 0..=input.len() {
+            // The set grows as we go, so we can't hold on to an iterator
+            let mut k = 0;
+            while k < chart.sets[i].len() {
+                let item = chart.sets[i][k];
+                k += 1;
+
+                match grammar.alternatives(item.rule)[item.alt].get(item.dot) {
+                    // Completion: advance everyone who was waiting on this rule
+                    None => {
+                        let mut p = 0;
+                        while p < chart.sets[item.origin].len() {
+                            let parent = chart.sets[item.origin][p];
+                            p += 1;
+
+                            if chart.expects(parent) == Some(&Symbol::Rule(item.rule)) {
+                                chart.add(i, parent.advance());
+                            }
+                        }
+                    }
+
+                    // Prediction, skipping over rules that can match nothing
+                    Some(&Symbol::Rule(sub)) => {
+                        for alt in 0..grammar.alternatives(sub).len() {
+                            chart.add(
+                                i,
+                                Item {
+                                    rule: sub,
+                                    alt,
+                                    dot: 0,
+                                    origin: i,
+                                },
+                            );
+                        }
+
+                        if nullable[sub] {
+                            chart.add(i, item.advance());
+                        }
+                    }
+
+                    // Scanning
+                    Some(Symbol::Terminal(t)) => {
+                        if input[i..].starts_with(t.as_bytes()) {
+                            chart.add(i + t.len(), item.advance());
+                        }
+                    }
+                }
+            }
+        }
+
+        chart
+    }
+
+    fn add(&mut self, i: usize, item: Item) {
+        if self.seen[i].insert(item) {
+            self.sets[i].push(item);
+        }
+    }
+
+    fn expects(&self, item: Item) -> Option<&'a Symbol> {
+        self.grammar.alternatives(item.rule)[item.alt].get(item.dot)
+    }
+
+    fn completed(&self, rule: usize, alt: usize, start: usize, end: usize) -> bool {
+        self.seen[end].contains(&Item {
+            rule,
+            alt,
+            dot: self.grammar.alternatives(rule)[alt].len(),
+            origin: start,
+        })
+    }
+
+    /// Whether the whole input matches the start rule
+    pub fn accepts(&self) -> bool {
+        (0..self.grammar.alternatives(self.start).len())
+            .any(|alt| self.completed(self.start, alt, 0, self.input.len()))
+    }
+
+    /// Explain why the input was rejected, or `None` if it matches
+    pub fn failure(&self) -> Option<Failure> {
+        if self.accepts() {
+            return None;
+        }
+
+        let offset = (0..self.sets.len())
+            .rev()
+            .find(|&i| !self.sets[i].is_empty())
+            .unwrap();
+
+        let mut expected = Vec::new();
+        for &item in &self.sets[offset] {
+            if let Some(Symbol::Terminal(t)) = self.expects(item) {
+                let entry = (item.rule, t.clone());
+                if !expected.contains(&entry) {
+                    expected.push(entry);
+                }
+            }
+        }
+
+        Some(Failure { offset, expected })
+    }
+
+    /// Build a parse tree for the whole input, if it matches
+    pub fn tree(&self) -> Option<ParseTree> {
+        self.build(self.start, 0, self.input.len(), &mut Vec::new())
+    }
+
+    /// Build a tree for `rule` matching exactly `start..end`. `path` holds the
+    /// nodes we're currently building, to avoid looping on cyclic rules.
+    fn build(
+        &self,
+        rule: usize,
+        start: usize,
+        end: usize,
+        path: &mut Vec<(usize, usize, usize)>,
+    ) -> Option<ParseTree> {
+        if path.contains(&(rule, start, end)) {
+            return None;
+        }
+        path.push((rule, start, end));
+
+        let tree = (0..self.grammar.alternatives(rule).len())
+            .filter(|&alt| self.completed(rule, alt, start, end))
+            .find_map(|alt| {
+                let dot = self.grammar.alternatives(rule)[alt].len();
+                let children = self.build_seq(rule, alt, dot, start, end, path)?;
+
+                Some(ParseTree::Rule {
+                    rule,
+                    alternative: alt,
+                    span: start..end,
+                    children,
+                })
+            });
+
+        path.pop();
+        tree
+    }
+
+    /// Build the children for the first `dot` symbols of an alternative,
+    /// matching exactly `start..end`, by working backwards from the last one
+    fn build_seq(
+        &self,
+        rule: usize,
+        alt: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+        path: &mut Vec<(usize, usize, usize)>,
+    ) -> Option<Vec<ParseTree>> {
+        if dot == 0 {
+            return if start == end { Some(Vec::new()) } else { None };
+        }
+
+        let prefix_ends_at = |mid: usize| {
+            mid >= start
+                && self.seen[mid].contains(&Item {
+                    rule,
+                    alt,
+                    dot: dot - 1,
+                    origin: start,
+                })
+        };
+
+        match &self.grammar.alternatives(rule)[alt][dot - 1] {
+            Symbol::Terminal(t) => {
+                let mid = end.checked_sub(t.len())?;
+                if !self.input[..end].ends_with(t.as_bytes()) || !prefix_ends_at(mid) {
+                    return None;
+                }
+
+                let mut children = self.build_seq(rule, alt, dot - 1, start, mid, path)?;
+                children.push(ParseTree::Terminal(mid..end));
+                Some(children)
+            }
+
+            &Symbol::Rule(sub) => (start..=end)
+                .rev()
+                .filter(|&mid| prefix_ends_at(mid))
+                .find_map(|mid| {
+                    let child = self.build(sub, mid, end, path)?;
+                    let mut children = self.build_seq(rule, alt, dot - 1, start, mid, path)?;
+                    children.push(child);
+                    Some(children)
+                }),
+        }
+    }
+}
+
+/// Parse the input starting from the given rule, returning its parse tree if it matches
+pub fn parse(grammar: &Grammar, start: usize, input: &[u8]) -> Option<ParseTree> {
+    Chart::new(grammar, start, input).tree()
+}
@@ -1,123 +1,368 @@
-use std::{debug_assert_eq, hint::unreachable_unchecked, mem::MaybeUninit};
+use std::cell::Cell;
+use std::collections::HashMap;
 
-// POSSIBLE OPTIMIZATION: we're not really iterating over strings, just bits where "a" is 0 and "b" is 1
+pub mod bits;
+pub mod earley;
 
-use arrayvec::ArrayVec;
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    Rule(usize),
+    Terminal(String),
+}
 
-#[derive(Clone)]
-enum Rule {
-    Terminator(bool),
-    Seq(ArrayVec<[u8; 2]>),
-    Or(ArrayVec<[u8; 2]>, ArrayVec<[u8; 2]>),
+/// A context-free grammar. Rules are referred to by index, handed out in the
+/// order their names are first seen.
+#[derive(Debug, Clone, Default)]
+pub struct Grammar {
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+    /// The alternatives of each rule, which is empty if the rule was only ever
+    /// referenced and never defined
+    rules: Vec<Vec<Vec<Symbol>>>,
+    /// Whether some rule can reach itself without consuming any input, worked
+    /// out the first time it's needed and forgotten whenever a rule changes
+    left_recursive: Cell<Option<bool>>,
 }
 
-type Rules = [Option<Rule>; 0xFF];
+impl Grammar {
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut grammar = Self::default();
+        lines.into_iter().for_each(|line| grammar.add_rule(line));
+        grammar
+    }
+
+    /// Add a rule written as `name: sym sym | sym "terminal"`, replacing any
+    /// previous definition of the same rule
+    pub fn add_rule(&mut self, line: &str) {
+        let (lhs, rhs) = {
+            let mut it = line.splitn(2, ':');
+            (it.next().unwrap().trim(), it.next().unwrap())
+        };
 
-fn parse_rule(rule: &'static str) -> Rule {
-    let mut parts = rule.split(' ');
+        let rule = self.intern(lhs);
+        let mut alternatives = vec![Vec::new()];
+        let mut rhs = rhs.trim_start();
+
+        while let Some(ch) = rhs.chars().next() {
+            if ch == '|' {
+                alternatives.push(Vec::new());
+                rhs = &rhs[1..];
+            } else if ch == '"' {
+                let end = rhs[1..].find('"').expect("unterminated terminal") + 1;
+                let terminal = rhs[1..end].to_owned();
+                alternatives
+                    .last_mut()
+                    .unwrap()
+                    .push(Symbol::Terminal(terminal));
+                rhs = &rhs[end + 1..];
+            } else {
+                let end = rhs
+                    .find(|ch: char| ch.is_whitespace() || ch == '|' || ch == '"')
+                    .unwrap_or(rhs.len());
+                let sub = self.intern(&rhs[..end]);
+                alternatives.last_mut().unwrap().push(Symbol::Rule(sub));
+                rhs = &rhs[end..];
+            }
 
-    if rule.starts_with('"') {
-        debug_assert!(rule.ends_with('"'));
-        debug_assert_eq!(rule.len(), 3);
-        debug_assert!(rule == "\"b\"" || rule == "\"a\"");
-        return Rule::Terminator(rule == "\"b\"");
+            rhs = rhs.trim_start();
+        }
+
+        self.rules[rule] = alternatives;
+        self.left_recursive.set(None);
     }
 
-    let fst: ArrayVec<[u8; 2]> = parts
-        .by_ref()
-        .take_while(|&part| part != "|")
-        .map(|part| part.parse().unwrap())
-        .collect();
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&idx) = self.indices.get(name) {
+            return idx;
+        }
 
-    let snd: ArrayVec<[u8; 2]> = parts
-        .filter(|&part| part != "|")
-        .map(|part| part.parse().unwrap())
-        .collect();
+        let idx = self.names.len();
+        self.names.push(name.to_owned());
+        self.indices.insert(name.to_owned(), idx);
+        self.rules.push(Vec::new());
+        idx
+    }
 
-    if !snd.is_empty() {
-        Rule::Or(fst, snd)
-    } else {
-        Rule::Seq(fst)
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
     }
-}
 
-fn matches_seq<'a>(rules: &Rules, seq: &[u8], line: &'a [u8]) -> Option<&'a [u8]> {
-    seq.iter()
-        .try_fold(line, |acc, &rule| matches_by_idx(rules, rule, acc))
-}
+    pub fn name(&self, rule: usize) -> &str {
+        &self.names[rule]
+    }
 
-fn matches_by_idx<'a>(rules: &Rules, rule: u8, line: &'a [u8]) -> Option<&'a [u8]> {
-    match rules[rule as usize].as_ref() {
-        Some(Rule::Terminator(b)) => {
-            if !line.is_empty() && ((line[0] & 1) == 0) == *b {
-                Some(&line[1..])
-            } else {
-                None
+    pub fn alternatives(&self, rule: usize) -> &[Vec<Symbol>] {
+        &self.rules[rule]
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Which rules can match the empty string
+    pub fn nullable(&self) -> Vec<bool> {
+        let mut nullable = vec![false; self.len()];
+
+        loop {
+            let mut changed = false;
+
+            for (rule, alternatives) in self.rules.iter().enumerate() {
+                if nullable[rule] {
+                    continue;
+                }
+
+                if alternatives.iter().any(|alt| {
+                    alt.iter().all(|sym| match sym {
+                        Symbol::Rule(sub) => nullable[*sub],
+                        Symbol::Terminal(t) => t.is_empty(),
+                    })
+                }) {
+                    nullable[rule] = true;
+                    changed = true;
+                }
             }
-        }
 
-        Some(Rule::Seq(seq)) => matches_seq(rules, &seq, line),
+            if !changed {
+                break nullable;
+            }
+        }
+    }
 
-        Some(Rule::Or(fst, snd)) => {
-            matches_seq(rules, &fst, line).or_else(|| matches_seq(rules, &snd, line))
+    /// Whether some rule can end up matching itself at the same offset, like
+    /// `S: S "a" | "b"`, either directly or through rules that can match the
+    /// empty string
+    pub fn is_left_recursive(&self) -> bool {
+        if let Some(left_recursive) = self.left_recursive.get() {
+            return left_recursive;
         }
 
-        None => unsafe { unreachable_unchecked() },
+        // The rules each rule can call without consuming any input first
+        let nullable = self.nullable();
+        let calls: Vec<Vec<usize>> = self
+            .rules
+            .iter()
+            .map(|alternatives| {
+                let mut calls = Vec::new();
+                for alt in alternatives {
+                    for sym in alt {
+                        match sym {
+                            Symbol::Rule(sub) => {
+                                calls.push(*sub);
+                                if !nullable[*sub] {
+                                    break;
+                                }
+                            }
+                            Symbol::Terminal(t) => {
+                                if !t.is_empty() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                calls
+            })
+            .collect();
+
+        let mut visited = vec![false; self.len()];
+        let mut on_path = vec![false; self.len()];
+
+        let left_recursive = (0..self.len())
+            .any(|rule| !visited[rule] && has_cycle(rule, &calls, &mut visited, &mut on_path));
+
+        self.left_recursive.set(Some(left_recursive));
+        left_recursive
     }
 }
 
-#[inline]
-pub fn solve() -> (usize, usize) {
-    // SAFETY: this is literally in the MaybeUninit docs
-    let mut rules: [MaybeUninit<Option<Rule>>; 0xFF] =
-        unsafe { MaybeUninit::uninit().assume_init() };
-    for rule in &mut rules {
-        *rule = MaybeUninit::new(None);
-    }
-    let mut rules = unsafe { std::mem::transmute::<_, Rules>(rules) };
-
-    let mut input = include_str!("input.txt").lines();
-
-    input
-        .by_ref()
-        .take_while(|l| !l.is_empty())
-        .for_each(|line| {
-            let (lhs, rhs) = {
-                let mut it = line.splitn(2, ": ");
-                (it.next().unwrap(), it.next().unwrap())
-            };
-            let lhs = lhs.parse::<u8>().unwrap();
-            let rhs = parse_rule(rhs);
-            rules[lhs as usize] = Some(rhs);
-        });
+/// Depth-first search from `rule` for a cycle of rules calling each other, where
+/// `on_path` marks the rules we're in the middle of visiting
+fn has_cycle(
+    rule: usize,
+    calls: &[Vec<usize>],
+    visited: &mut [bool],
+    on_path: &mut [bool],
+) -> bool {
+    visited[rule] = true;
+    on_path[rule] = true;
 
-    let mut part1 = 0;
-    let mut part2 = 0;
+    let cycle = calls[rule]
+        .iter()
+        .any(|&sub| on_path[sub] || (!visited[sub] && has_cycle(sub, calls, visited, on_path)));
+
+    on_path[rule] = false;
+    cycle
+}
+
+/// Split a puzzle into the grammar and the messages to check
+pub fn parse_puzzle(input: &str) -> (Grammar, Vec<&str>) {
+    let mut lines = input.lines();
+    let grammar = Grammar::from_lines(lines.by_ref().take_while(|l| !l.is_empty()));
+
+    for (rule, alternatives) in grammar.rules.iter().enumerate() {
+        assert!(
+            !alternatives.is_empty(),
+            "rule {:?} is never defined",
+            grammar.name(rule)
+        );
+    }
+
+    (grammar, lines.collect())
+}
+
+pub fn parse_input() -> (Grammar, Vec<&'static str>) {
+    parse_puzzle(include_str!("input.txt"))
+}
 
-    input.map(str::as_bytes).for_each(|mut line| {
-        // Part 1: Straight up regex match
-        if matches_by_idx(&rules, 0, line) == Some(b"") {
-            part1 += 1;
+/// Memoized recognizer which, for every rule and starting offset in the line,
+/// computes the set of offsets at which a match of that rule can end.
+///
+/// Tracking every possible end instead of the first one means there's no need
+/// to backtrack, and as long as every rule consumes at least one byte before
+/// recursing, looping rules like `8: 42 | 42 8` terminate on their own.
+/// It can't handle left-recursive grammars, which `matches` hands to the
+/// `earley` parser instead.
+struct Recognizer<'a> {
+    grammar: &'a Grammar,
+    line: &'a [u8],
+    memo: Vec<Option<Vec<usize>>>,
+}
+
+impl<'a> Recognizer<'a> {
+    fn new(grammar: &'a Grammar, line: &'a [u8]) -> Self {
+        Self {
+            grammar,
+            line,
+            memo: vec![None; grammar.len() * (line.len() + 1)],
         }
+    }
 
-        // Part 2: Count "left parens", Count "right parens", we should have at
-        //         least one of each and more left than right
-        let mut left = 0;
-        while let Some(rest) = matches_by_idx(&rules, 42, line) {
-            left += 1;
-            line = rest;
+    fn ends_seq(&mut self, seq: &[Symbol], start: usize) -> Vec<usize> {
+        let mut current = vec![start];
+
+        for sym in seq {
+            let mut next = Vec::new();
+
+            for &pos in &current {
+                for end in self.ends(sym, pos) {
+                    if !next.contains(&end) {
+                        next.push(end);
+                    }
+                }
+            }
+
+            current = next;
+            if current.is_empty() {
+                break;
+            }
         }
 
-        let mut right = 0;
-        while let Some(rest) = matches_by_idx(&rules, 31, line) {
-            right += 1;
-            line = rest;
+        current
+    }
+
+    fn ends(&mut self, sym: &Symbol, start: usize) -> Vec<usize> {
+        let rule = match sym {
+            Symbol::Terminal(t) => {
+                return if self.line[start..].starts_with(t.as_bytes()) {
+                    vec![start + t.len()]
+                } else {
+                    Vec::new()
+                };
+            }
+
+            Symbol::Rule(rule) => *rule,
+        };
+
+        let key = rule * (self.line.len() + 1) + start;
+        if let Some(ends) = &self.memo[key] {
+            return ends.clone();
         }
 
-        if line.is_empty() && left >= 1 && right >= 1 && right < left && (left - right) >= 1 {
-            part2 += 1;
+        let mut ends = Vec::new();
+        for alt in self.grammar.alternatives(rule) {
+            for end in self.ends_seq(alt, start) {
+                if !ends.contains(&end) {
+                    ends.push(end);
+                }
+            }
         }
-    });
+
+        self.memo[key] = Some(ends.clone());
+        ends
+    }
+}
+
+/// Check whether the whole line matches the given rule. This uses the memoized
+/// recognizer unless the grammar is left-recursive, in which case it falls back
+/// to the slower Earley parser.
+pub fn matches(grammar: &Grammar, rule: usize, line: &[u8]) -> bool {
+    if grammar.is_left_recursive() {
+        return earley::Chart::new(grammar, rule, line).accepts();
+    }
+
+    Recognizer::new(grammar, line)
+        .ends(&Symbol::Rule(rule), 0)
+        .contains(&line.len())
+}
+
+/// Count the messages matching rule 0 with the general recognizer, both as
+/// given and with the looping versions of rules 8 and 11
+pub fn solve_general(mut grammar: Grammar, messages: &[&str]) -> (usize, usize) {
+    let start = grammar.index("0").unwrap();
+
+    let part1 = messages
+        .iter()
+        .filter(|line| matches(&grammar, start, line.as_bytes()))
+        .count();
+
+    // Part 2: Replace the rules with their looping versions
+    grammar.add_rule("8: 42 | 42 8");
+    grammar.add_rule("11: 42 31 | 42 11 31");
+
+    let part2 = messages
+        .iter()
+        .filter(|line| matches(&grammar, start, line.as_bytes()))
+        .count();
 
     (part1, part2)
 }
+
+/// Same as `solve_general`, but using chunk lookup tables. Returns `None` if
+/// the grammar or the messages aren't suitable for them.
+pub fn solve_bitpacked(grammar: &Grammar, messages: &[&str]) -> Option<(usize, usize)> {
+    let tables = bits::ChunkTables::new(grammar)?;
+
+    if messages.iter().any(|line| line.len() > 128) {
+        return None;
+    }
+
+    let mut part1 = 0;
+    let mut part2 = 0;
+
+    // Messages that can't be packed contain bytes no terminal can match
+    messages
+        .iter()
+        .filter_map(|line| bits::Message::pack(line.as_bytes()))
+        .for_each(|msg| {
+            if tables.matches_part1(msg) {
+                part1 += 1;
+            }
+
+            if tables.matches_part2(msg) {
+                part2 += 1;
+            }
+        });
+
+    Some((part1, part2))
+}
+
+#[inline]
+pub fn solve() -> (usize, usize) {
+    let (grammar, messages) = parse_input();
+
+    solve_bitpacked(&grammar, &messages).unwrap_or_else(|| solve_general(grammar, &messages))
+}
@@ -0,0 +1,36 @@
+//! Print every round of a game of Combat
+//!
+//! Usage: day22_replay [1|2] [text|json] [FILE]
+//!
+//! Plays regular Combat for 1 and Recursive Combat for 2, with the decks from
+//! FILE or the puzzle input. FILE may have any number of players.
+
+use std::{env, fs};
+
+use day22::{parse_decks, replay, replay_game, HighestCard};
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let part = args.next().unwrap_or_else(|| "2".to_owned());
+    let format = args.next().unwrap_or_else(|| "text".to_owned());
+    let input = match args.next() {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => include_str!("../input.txt").to_owned(),
+    };
+
+    let recursive = match part.as_str() {
+        "1" => false,
+        "2" => true,
+        _ => panic!("unknown part {:?}, expected 1 or 2", part),
+    };
+    let (score, events) = replay_game(parse_decks(&input), &HighestCard, recursive);
+
+    match format.as_str() {
+        "text" => print!("{}", replay::text(&events, recursive)),
+        "json" => print!("{}", replay::json(&events)),
+        _ => panic!("unknown format {:?}, expected text or json", format),
+    }
+
+    eprintln!("score: {}", score);
+}
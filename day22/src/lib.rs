@@ -1,141 +1,495 @@
-use std::collections::{HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::iter::FromIterator;
 
-fn play_combat(player1: &mut VecDeque<u8>, player2: &mut VecDeque<u8>) {
-    if player1.front() > player2.front() {
-        let f = player1.pop_front().unwrap();
-        player1.push_back(f);
-        player1.push_back(player2.pop_front().unwrap());
-    } else {
-        let f = player2.pop_front().unwrap();
-        player2.push_back(f);
-        player2.push_back(player1.pop_front().unwrap());
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+pub mod replay;
+use replay::Event;
+
+/// Bases of the two polynomials used for the rolling hashes of a deck. They're
+/// odd, so they have inverses modulo 2^64.
+const HASH_BASES: [u64; 2] = [0x100_0000_01b3, 0x9e37_79b9_7f4a_7c15];
+
+/// The inverses of `HASH_BASES` modulo 2^64
+const HASH_BASES_INV: [u64; 2] = [inverse(HASH_BASES[0]), inverse(HASH_BASES[1])];
+
+/// The inverse of an odd number modulo 2^64, by Newton's method: each step
+/// doubles the number of correct low bits, and an odd number is its own inverse
+/// modulo 8
+const fn inverse(n: u64) -> u64 {
+    let mut inv = n;
+    let mut i = 0;
+    while i < 5 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(n.wrapping_mul(inv)));
+        i += 1;
+    }
+    inv
+}
+
+/// A deck of cards stored in a ring buffer, which keeps two independent rolling
+/// hashes of its contents up to date as cards are drawn and added
+#[derive(Clone)]
+pub struct Deck {
+    /// Always a power of two long, so that wrapping around is cheap
+    cards: Box<[u8]>,
+    head: usize,
+    len: usize,
+    /// The cards as the digits of a number in each of `HASH_BASES`, wrapping
+    /// on overflow
+    hashes: [u64; 2],
+    /// Each of `HASH_BASES` to the power of `len`
+    powers: [u64; 2],
+}
+
+impl Deck {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// An empty deck with room for the given number of cards. Every card in
+    /// the game can end up in the same deck, so sizing decks for all of them
+    /// saves growing them mid-game.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            cards: vec![0; capacity.next_power_of_two()].into_boxed_slice(),
+            head: 0,
+            len: 0,
+            hashes: [0; 2],
+            powers: [1; 2],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn mask(&self) -> usize {
+        self.cards.len() - 1
+    }
+
+    pub fn front(&self) -> Option<u8> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.cards[self.head])
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<u8> {
+        let card = self.front()?;
+        self.len -= 1;
+        self.head = (self.head + 1) & self.mask();
+        let digits = self.hashes.iter_mut().zip(&mut self.powers);
+        for ((hash, power), base_inv) in digits.zip(&HASH_BASES_INV) {
+            *power = power.wrapping_mul(*base_inv);
+            *hash = hash.wrapping_sub(u64::from(card).wrapping_mul(*power));
+        }
+        Some(card)
+    }
+
+    pub fn push_back(&mut self, card: u8) {
+        if self.len == self.cards.len() {
+            let mut grown = Self::with_capacity(self.len * 2);
+            grown.cards[..self.len].copy_from_slice(&self.iter().collect::<Vec<_>>());
+            grown.len = self.len;
+            grown.hashes = self.hashes;
+            grown.powers = self.powers;
+            *self = grown;
+        }
+
+        let tail = (self.head + self.len) & self.mask();
+        self.cards[tail] = card;
+        self.len += 1;
+        let digits = self.hashes.iter_mut().zip(&mut self.powers);
+        for ((hash, power), base) in digits.zip(&HASH_BASES) {
+            *power = power.wrapping_mul(*base);
+            *hash = hash.wrapping_mul(*base).wrapping_add(u64::from(card));
+        }
+    }
+
+    /// Empty the deck, keeping its buffer around to be reused
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.hashes = [0; 2];
+        self.powers = [1; 2];
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.len).map(move |i| self.cards[(self.head + i) & self.mask()])
+    }
+
+    /// The deck's two rolling hashes. Together they make a 128-bit hash, so
+    /// two different decks colliding is vanishingly unlikely.
+    pub fn hashes(&self) -> [u64; 2] {
+        self.hashes
     }
 }
 
-fn load_deck(deck: &str) -> VecDeque<u8> {
-    deck.lines()
-        .skip(1)
-        .map(|n| n.parse::<u8>().unwrap())
+impl Default for Deck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromIterator<u8> for Deck {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut deck = Self::new();
+        iter.into_iter().for_each(|card| deck.push_back(card));
+        deck
+    }
+}
+
+/// Decides who wins a round of Combat, unless it's settled by a sub-game
+pub trait WinnerRule {
+    /// Pick the winner out of the cards played this round, given as pairs of
+    /// player index and card
+    fn winner(&self, played: &[(usize, u8)]) -> usize;
+
+    /// Put the played cards in the order in which the winner adds them to the
+    /// bottom of their deck: their own card first, then the others from
+    /// highest to lowest. A winner who had no card to play just takes the others.
+    fn arrange(&self, winner: usize, played: &mut [(usize, u8)]) {
+        // Most games have two players, and comparing two cards is a lot faster
+        // than sorting them
+        if let [(first, card1), (second, card2)] = *played {
+            if second == winner || (first != winner && card2 > card1) {
+                played.swap(0, 1);
+            }
+        } else {
+            played.sort_unstable_by_key(|&(player, card)| (player != winner, Reverse(card)));
+        }
+    }
+}
+
+/// The puzzle's rule: the highest card takes all
+pub struct HighestCard;
+
+impl WinnerRule for HighestCard {
+    fn winner(&self, played: &[(usize, u8)]) -> usize {
+        played.iter().max_by_key(|&&(_, card)| card).unwrap().0
+    }
+}
+
+/// Parse the decks, one per player, each sized to hold every card in the game
+pub fn parse_decks(input: &str) -> Vec<Deck> {
+    let cards: Vec<Vec<u8>> = input
+        .trim()
+        .split("\n\n")
+        .filter(|deck| !deck.is_empty())
+        .map(|deck| {
+            deck.lines()
+                .skip(1)
+                .map(|n| {
+                    n.trim()
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|&card| card > 0)
+                        .unwrap_or_else(|| panic!("card {:?} should be a number from 1 to 255", n))
+                })
+                .collect()
+        })
+        .collect();
+
+    assert!(
+        cards.iter().any(|deck| !deck.is_empty()),
+        "at least one player needs some cards"
+    );
+
+    let total = cards.iter().map(Vec::len).sum();
+    cards
+        .into_iter()
+        .map(|deck| {
+            let mut d = Deck::with_capacity(total);
+            deck.into_iter().for_each(|card| d.push_back(card));
+            d
+        })
         .collect()
 }
 
-pub fn load_input() -> (VecDeque<u8>, VecDeque<u8>) {
-    let mut decks = include_str!("input.txt").split("\n\n").map(load_deck);
-    (decks.next().unwrap(), decks.next().unwrap())
+pub fn load_input() -> Vec<Deck> {
+    parse_decks(include_str!("input.txt"))
 }
 
-fn calculate_score(deck: &VecDeque<u8>) -> usize {
+fn calculate_score(deck: &Deck) -> usize {
     deck.iter()
-        .rev()
         .enumerate()
-        .map(|(a, &b)| (a + 1) * b as usize)
+        .map(|(a, b)| (deck.len() - a) * b as usize)
         .sum::<usize>()
 }
 
-pub fn solve_part1(mut player1: VecDeque<u8>, mut player2: VecDeque<u8>) -> usize {
-    while !player1.is_empty() && !player2.is_empty() {
-        play_combat(&mut player1, &mut player2);
+/// Push an event to the log if we're keeping one. The event is built lazily
+/// so that we don't copy the decks around when we're not.
+fn log_event(log: &mut Option<Vec<Event>>, event: impl FnOnce() -> Event) {
+    if let Some(log) = log {
+        log.push(event());
     }
-    let winner = if player1.is_empty() { player2 } else { player1 };
-    calculate_score(&winner)
 }
 
-struct RecursiveCombatPlayer {
-    history: HashSet<Vec<u8>>,
-    player1: VecDeque<u8>,
-    player2: VecDeque<u8>,
+/// Identifies a set of decks by combining their rolling hashes and lengths.
+/// It's 128 bits wide, so we treat two equal snapshots as the same decks
+/// without comparing the cards: with the few million snapshots a game takes,
+/// the odds of a collision are around 10^-26.
+type Snapshot = [u64; 2];
+
+fn snapshot(decks: &[Deck]) -> Snapshot {
+    let mut snapshot: Snapshot = [0; 2];
+    for deck in decks {
+        for (i, hash) in snapshot.iter_mut().enumerate() {
+            *hash = hash
+                .wrapping_mul(HASH_BASES[i])
+                .wrapping_add(deck.hashes()[i])
+                .wrapping_mul(HASH_BASES[i])
+                .wrapping_add(deck.len() as u64);
+        }
+    }
+    snapshot
 }
 
-#[derive(Debug)]
-enum Winner {
-    None,
-    Player1,
-    Player2,
+/// State shared between a game and all of its sub-games
+struct Context<'a> {
+    rule: &'a dyn WinnerRule,
+    /// Whether rounds can be settled by sub-games, and repeated rounds end the game
+    recursive: bool,
+    /// The winner of every sub-game played so far, keyed by the decks it started with
+    winners: HashMap<Snapshot, usize>,
+    /// How many games have been started, to number them like the puzzle does
+    games: usize,
+    /// Players left over from finished sub-games, so that new ones can reuse
+    /// their decks and history instead of allocating their own
+    spare: Vec<CombatPlayer>,
+    log: Option<Vec<Event>>,
 }
 
-impl RecursiveCombatPlayer {
-    fn play_round(&mut self) -> Winner {
-        let mut snapshot = Vec::new();
-        snapshot.extend(self.player1.iter().copied());
-        snapshot.push(0xF0);
-        snapshot.extend(self.player2.iter().copied());
-        if !self.history.insert(snapshot) {
-            return Winner::Player1;
+impl<'a> Context<'a> {
+    fn new(rule: &'a dyn WinnerRule, recursive: bool, log: bool) -> Self {
+        Self {
+            rule,
+            recursive,
+            winners: HashMap::default(),
+            games: 0,
+            spare: Vec::new(),
+            log: if log { Some(Vec::new()) } else { None },
         }
+    }
+}
 
-        let card1 = self.player1.pop_front().unwrap();
-        let card2 = self.player2.pop_front().unwrap();
+struct CombatPlayer {
+    history: HashSet<Snapshot>,
+    decks: Vec<Deck>,
+    /// The cards played this round, as pairs of player index and card, kept
+    /// around so that rounds don't allocate
+    played: Vec<(usize, u8)>,
+    game: usize,
+    depth: usize,
+    round: usize,
+}
 
-        if self.player1.len() >= card1 as usize && self.player2.len() >= card2 as usize {
-            let subdeck1 = self.player1.iter().take(card1 as usize).copied().collect();
-            let subdeck2 = self.player2.iter().take(card2 as usize).copied().collect();
-            let mut subgame = Self {
-                history: Default::default(),
-                player1: subdeck1,
-                player2: subdeck2,
-            };
+impl CombatPlayer {
+    fn new(decks: Vec<Deck>) -> Self {
+        Self {
+            history: Default::default(),
+            decks,
+            played: Vec::new(),
+            game: 0,
+            depth: 0,
+            round: 0,
+        }
+    }
 
-            match subgame.play_game() {
-                Winner::Player1 => {
-                    self.player1.push_back(card1);
-                    self.player1.push_back(card2);
-                }
-                Winner::Player2 => {
-                    self.player2.push_back(card2);
-                    self.player2.push_back(card1);
+    /// Play a round, returning the winner of the game if it ended early
+    fn play_round(&mut self, ctx: &mut Context) -> Option<usize> {
+        let (game, depth) = (self.game, self.depth);
+        self.round += 1;
+        let round = self.round;
+
+        // Player 1 wins a loop, or whoever comes first of those still playing
+        // if they're out
+        if ctx.recursive && !self.history.insert(snapshot(&self.decks)) {
+            let winner = self.decks.iter().position(|deck| !deck.is_empty());
+            log_event(&mut ctx.log, || Event::LoopDetected {
+                game,
+                depth,
+                round,
+                winner: winner.unwrap() + 1,
+            });
+            return winner;
+        }
+
+        // Players who have run out of cards are out of the game
+        self.played.clear();
+        for (player, deck) in self.decks.iter_mut().enumerate() {
+            if let Some(card) = deck.pop_front() {
+                self.played.push((player, card));
+            }
+        }
+
+        log_event(&mut ctx.log, || {
+            let mut cards = vec![None; self.decks.len()];
+            let mut decks: Vec<Vec<u8>> = self
+                .decks
+                .iter()
+                .map(|deck| deck.iter().collect())
+                .collect();
+            for &(player, card) in &self.played {
+                cards[player] = Some(card);
+                decks[player].insert(0, card);
+            }
+
+            Event::Round {
+                game,
+                depth,
+                round,
+                decks,
+                cards,
+            }
+        });
+
+        // A sub-game needs some cards to play with, which only matters if
+        // everyone played a 0
+        let recurse = ctx.recursive
+            && self.played.iter().any(|&(_, card)| card > 0)
+            && self
+                .played
+                .iter()
+                .all(|&(player, card)| self.decks[player].len() >= card as usize);
+
+        let winner = if recurse {
+            let mut sub = ctx.spare.pop().unwrap_or_else(|| Self::new(Vec::new()));
+            sub.decks.resize_with(self.decks.len(), Deck::new);
+            sub.decks.iter_mut().for_each(Deck::clear);
+            for &(player, card) in &self.played {
+                for card in self.decks[player].iter().take(card as usize) {
+                    sub.decks[player].push_back(card);
                 }
-                Winner::None => unreachable!(),
             }
-        } else if card1 > card2 {
-            self.player1.push_back(card1);
-            self.player1.push_back(card2);
+            let key = snapshot(&sub.decks);
+
+            // When logging, replay every sub-game so the log matches the puzzle's
+            let cached = match ctx.log {
+                None => ctx.winners.get(&key).copied(),
+                Some(_) => None,
+            };
+
+            let winner = match cached {
+                Some(winner) => winner,
+                None => {
+                    log_event(&mut ctx.log, || Event::SubGame { game, depth });
+                    let winner = sub.play_game(ctx, depth + 1);
+                    ctx.winners.insert(key, winner);
+                    winner
+                }
+            };
+            ctx.spare.push(sub);
+            winner
         } else {
-            self.player2.push_back(card2);
-            self.player2.push_back(card1);
+            ctx.rule.winner(&self.played)
+        };
+
+        ctx.rule.arrange(winner, &mut self.played);
+        for &(_, card) in &self.played {
+            self.decks[winner].push_back(card);
         }
 
-        Winner::None
+        log_event(&mut ctx.log, || Event::RoundWon {
+            game,
+            depth,
+            round,
+            winner: winner + 1,
+        });
+
+        None
     }
 
-    // p1 won = true
-    // p2 won = false
-    fn play_game(&mut self) -> Winner {
-        while !self.player1.is_empty() && !self.player2.is_empty() {
-            match self.play_round() {
-                Winner::None => {}
-                winner => return winner,
+    /// Play a game with the decks we're holding, `depth` sub-games down from
+    /// the main game, returning the winner
+    fn play_game(&mut self, ctx: &mut Context, depth: usize) -> usize {
+        ctx.games += 1;
+        self.history.clear();
+        self.game = ctx.games;
+        self.depth = depth;
+        self.round = 0;
+
+        let game = self.game;
+        log_event(&mut ctx.log, || Event::GameStart { game, depth });
+
+        let winner = loop {
+            let mut players = self
+                .decks
+                .iter()
+                .enumerate()
+                .filter(|(_, deck)| !deck.is_empty());
+
+            if let (Some((winner, _)), None) = (players.next(), players.next()) {
+                break winner;
             }
-        }
-        if !self.player1.is_empty() {
-            Winner::Player1
-        } else {
-            Winner::Player2
-        }
+
+            if let Some(winner) = self.play_round(ctx) {
+                break winner;
+            }
+        };
+
+        log_event(&mut ctx.log, || Event::GameWon {
+            game,
+            depth,
+            winner: winner + 1,
+            decks: self
+                .decks
+                .iter()
+                .map(|deck| deck.iter().collect())
+                .collect(),
+        });
+
+        winner
     }
 }
 
-pub fn solve_part2(player1: VecDeque<u8>, player2: VecDeque<u8>) -> usize {
-    let mut game = RecursiveCombatPlayer {
-        history: Default::default(),
-        player1,
-        player2,
-    };
+fn play_combat(decks: Vec<Deck>, ctx: &mut Context) -> usize {
+    assert!(
+        decks.iter().any(|deck| !deck.is_empty()),
+        "at least one player needs some cards"
+    );
 
-    let winner = match game.play_game() {
-        Winner::Player1 => game.player1,
-        Winner::Player2 => game.player2,
+    let mut game = CombatPlayer::new(decks);
+    let winner = game.play_game(ctx, 0);
+    calculate_score(&game.decks[winner])
+}
+
+/// Play a game of Combat with any number of players, returning the winner's
+/// score. If `recursive` is set, the rules of Recursive Combat apply.
+pub fn score(decks: Vec<Deck>, rule: &dyn WinnerRule, recursive: bool) -> usize {
+    play_combat(decks, &mut Context::new(rule, recursive, false))
+}
 
-        Winner::None => unreachable!(),
-    };
+/// Same as `score`, but also return every event of the game, including those
+/// of every sub-game
+pub fn replay_game(
+    decks: Vec<Deck>,
+    rule: &dyn WinnerRule,
+    recursive: bool,
+) -> (usize, Vec<Event>) {
+    let mut ctx = Context::new(rule, recursive, true);
+    let score = play_combat(decks, &mut ctx);
+    (score, ctx.log.unwrap())
+}
+
+pub fn solve_part1(decks: Vec<Deck>) -> usize {
+    score(decks, &HighestCard, false)
+}
 
-    calculate_score(&winner)
+pub fn solve_part2(decks: Vec<Deck>) -> usize {
+    score(decks, &HighestCard, true)
 }
 
 #[inline]
 pub fn solve() -> (usize, usize) {
-    let (player1, player2) = load_input();
+    let decks = load_input();
 
-    let part1 = solve_part1(player1.clone(), player2.clone());
-    let part2 = solve_part2(player1, player2);
+    let part1 = solve_part1(decks.clone());
+    let part2 = solve_part2(decks);
     (part1, part2)
 }
@@ -2,4 +2,4 @@ fn main() {
     let (part1, part2) = day22::solve();
     println!("{}", part1);
     println!("{}", part2);
-}
\ No newline at end of file
+}
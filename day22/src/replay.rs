@@ -0,0 +1,257 @@
+//! Record of everything that happened during a game, which can be printed in
+//! the same format as the worked examples or as JSON
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    GameStart {
+        game: usize,
+        depth: usize,
+    },
+
+    /// A round starts with the given decks, and each player plays their top
+    /// card, or nothing if they're out of the game
+    Round {
+        game: usize,
+        depth: usize,
+        round: usize,
+        decks: Vec<Vec<u8>>,
+        cards: Vec<Option<u8>>,
+    },
+
+    /// The events up to the matching `GameWon` belong to a sub-game
+    SubGame {
+        game: usize,
+        depth: usize,
+    },
+
+    /// `winner` is the number of the player who won, starting from 1
+    RoundWon {
+        game: usize,
+        depth: usize,
+        round: usize,
+        winner: usize,
+    },
+
+    /// The decks are the same as they were at the start of an earlier round,
+    /// so `winner` wins the game: player 1, unless they're already out
+    LoopDetected {
+        game: usize,
+        depth: usize,
+        round: usize,
+        winner: usize,
+    },
+
+    GameWon {
+        game: usize,
+        depth: usize,
+        winner: usize,
+        decks: Vec<Vec<u8>>,
+    },
+}
+
+fn join(cards: &[u8]) -> String {
+    cards
+        .iter()
+        .map(|card| card.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn write_decks(out: &mut String, decks: &[Vec<u8>]) {
+    for (player, deck) in decks.iter().enumerate() {
+        writeln!(out, "Player {}'s deck: {}", player + 1, join(deck)).unwrap();
+    }
+}
+
+/// Format the events like the puzzle's worked examples. Regular Combat
+/// doesn't mention games, so `recursive` selects which example to follow.
+pub fn text(events: &[Event], recursive: bool) -> String {
+    let mut out = String::new();
+
+    // The games waiting on the outcome of a sub-game
+    let mut parents = Vec::new();
+
+    for event in events {
+        match event {
+            Event::GameStart { game, .. } => {
+                if recursive {
+                    writeln!(out, "=== Game {} ===\n", game).unwrap();
+                }
+            }
+
+            Event::Round {
+                game,
+                round,
+                decks,
+                cards,
+                ..
+            } => {
+                if recursive {
+                    writeln!(out, "-- Round {} (Game {}) --", round, game).unwrap();
+                } else {
+                    writeln!(out, "-- Round {} --", round).unwrap();
+                }
+                write_decks(&mut out, decks);
+                for (player, card) in cards.iter().enumerate() {
+                    if let Some(card) = card {
+                        writeln!(out, "Player {} plays: {}", player + 1, card).unwrap();
+                    }
+                }
+            }
+
+            Event::SubGame { game, .. } => {
+                parents.push(*game);
+                writeln!(out, "Playing a sub-game to determine the winner...\n").unwrap();
+            }
+
+            Event::RoundWon {
+                game,
+                round,
+                winner,
+                ..
+            } => {
+                if recursive {
+                    writeln!(
+                        out,
+                        "Player {} wins round {} of game {}!\n",
+                        winner, round, game
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(out, "Player {} wins the round!\n", winner).unwrap();
+                }
+            }
+
+            Event::LoopDetected {
+                game,
+                round,
+                winner,
+                ..
+            } => {
+                writeln!(
+                    out,
+                    "-- Round {} (Game {}) --\nThe decks repeat an earlier round, so player {} wins game {}!",
+                    round, game, winner, game
+                )
+                .unwrap();
+            }
+
+            Event::GameWon {
+                game,
+                depth,
+                winner,
+                decks,
+            } => {
+                if recursive {
+                    writeln!(out, "The winner of game {} is player {}!\n", game, winner).unwrap();
+                }
+
+                if *depth == 0 {
+                    writeln!(out, "\n== Post-game results ==").unwrap();
+                    write_decks(&mut out, decks);
+                } else {
+                    let parent = parents.pop().unwrap();
+                    writeln!(out, "...anyway, back to game {}.", parent).unwrap();
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn json_cards(cards: &[u8]) -> String {
+    format!("[{}]", join(cards))
+}
+
+fn json_decks(decks: &[Vec<u8>]) -> String {
+    let decks: Vec<_> = decks.iter().map(|deck| json_cards(deck)).collect();
+    format!("[{}]", decks.join(", "))
+}
+
+/// Format the events as a JSON array, one event per line
+pub fn json(events: &[Event]) -> String {
+    let mut out = String::from("[\n");
+
+    for (i, event) in events.iter().enumerate() {
+        out.push_str("  ");
+
+        match event {
+            Event::GameStart { game, depth } => write!(
+                out,
+                r#"{{"event": "game_start", "game": {}, "depth": {}}}"#,
+                game, depth
+            ),
+
+            Event::Round {
+                game,
+                depth,
+                round,
+                decks,
+                cards,
+            } => write!(
+                out,
+                r#"{{"event": "round", "game": {}, "depth": {}, "round": {}, "decks": {}, "cards": [{}]}}"#,
+                game,
+                depth,
+                round,
+                json_decks(decks),
+                cards
+                    .iter()
+                    .map(|card| card.map_or_else(|| "null".to_owned(), |card| card.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+
+            Event::SubGame { game, depth } => write!(
+                out,
+                r#"{{"event": "sub_game", "game": {}, "depth": {}}}"#,
+                game, depth
+            ),
+
+            Event::RoundWon {
+                game,
+                depth,
+                round,
+                winner,
+            } => write!(
+                out,
+                r#"{{"event": "round_won", "game": {}, "depth": {}, "round": {}, "winner": {}}}"#,
+                game, depth, round, winner
+            ),
+
+            Event::LoopDetected {
+                game,
+                depth,
+                round,
+                winner,
+            } => write!(
+                out,
+                r#"{{"event": "loop_detected", "game": {}, "depth": {}, "round": {}, "winner": {}}}"#,
+                game, depth, round, winner
+            ),
+
+            Event::GameWon {
+                game,
+                depth,
+                winner,
+                decks,
+            } => write!(
+                out,
+                r#"{{"event": "game_won", "game": {}, "depth": {}, "winner": {}, "decks": {}}}"#,
+                game,
+                depth,
+                winner,
+                json_decks(decks)
+            ),
+        }
+        .unwrap();
+
+        out.push_str(if i + 1 == events.len() { "\n" } else { ",\n" });
+    }
+
+    out.push_str("]\n");
+    out
+}
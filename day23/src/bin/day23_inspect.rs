@@ -0,0 +1,26 @@
+//! Play a game and show the state of the ring afterwards
+//!
+//! Usage: day23_inspect CUPS MOVES [N] [LABEL]
+//!
+//! Prints the current cup and the N cups (10 by default) clockwise of the cup
+//! labelled LABEL (1 by default).
+
+use std::env;
+
+use day23::{parse_input, Cups, PICKUP_NUM};
+
+fn main() {
+    let mut args = env::args().skip(1).map(|arg| arg.parse::<usize>().unwrap());
+    let usage = "usage: day23_inspect CUPS MOVES [N] [LABEL]";
+
+    let total = args.next().expect(usage);
+    let moves = args.next().expect(usage);
+    let n = args.next().unwrap_or(10);
+    let label = args.next().unwrap_or(1) as u32;
+
+    let mut cups = Cups::new(&parse_input(), total);
+    cups.play::<PICKUP_NUM>(moves);
+
+    println!("current cup: {}", cups.current_cup());
+    println!("after cup {}: {}", label, cups.display_after(label, n));
+}
@@ -1,116 +1,226 @@
-#![feature(new_uninit)]
-
-use std::fmt::Display;
+use std::fmt::{self, Display};
 
 /// How many cups do we pick up?
-const PICKUP_NUM: usize = 3;
+pub const PICKUP_NUM: usize = 3;
 
 const MOVES_PART1: usize = 100;
 
-const CARDS_PART1: usize = 10;
-const CARDS_PART2: usize = 1_000_001;
+const CUPS_PART1: usize = 9;
+const CUPS_PART2: usize = 1_000_000;
 
 const MOVES_PART2: usize = 10_000_000;
 
-pub struct CupsDisplay {
-    successors: [u8; CARDS_PART1],
+/// A ring of cups labelled from 1 up to the number of cups, stored as the
+/// label of the cup clockwise of each cup
+pub struct Cups {
+    successors: Vec<u32>,
+    current_cup: u32,
 }
 
-impl Display for CupsDisplay {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut n = 1;
+impl Cups {
+    /// Lay out the given cups, followed by every label after the highest
+    /// given one up to `total`
+    pub fn new(initial_cups: &[u8], total: usize) -> Self {
+        assert!(!initial_cups.is_empty(), "there must be at least one cup");
+        assert!(
+            total >= initial_cups.len(),
+            "can't fit {} starting cups in a ring of {}",
+            initial_cups.len(),
+            total
+        );
+
+        let mut labels = initial_cups.to_vec();
+        labels.sort_unstable();
+        assert!(
+            labels.iter().copied().eq(1..=initial_cups.len() as u8),
+            "the cups given should be labelled from 1 to {} with no repeats",
+            initial_cups.len()
+        );
+
+        let current_cup = u32::from(initial_cups[0]);
+
+        // Label 0 is never used, but it saves us an off-by-one on every access
+        let mut successors = vec![0; total + 1];
+
+        let last = initial_cups
+            .iter()
+            .skip(1)
+            .map(|&n| u32::from(n))
+            .chain(initial_cups.len() as u32 + 1..=total as u32)
+            .fold(current_cup, |prev, cur| {
+                successors[prev as usize] = cur;
+                cur
+            });
+        successors[last as usize] = current_cup;
+
+        Self {
+            successors,
+            current_cup,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.successors.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The cup the next move will start from
+    pub fn current_cup(&self) -> u32 {
+        self.current_cup
+    }
+
+    /// Iterate clockwise over the cups after the one with the given label,
+    /// stopping before going all the way around to it
+    pub fn after(&self, label: u32) -> After<'_> {
+        assert!((1..=self.len() as u32).contains(&label), "no cup {}", label);
+
+        After {
+            successors: &self.successors,
+            cup: label,
+            remaining: self.len() - 1,
+        }
+    }
+
+    /// Display `n` cups clockwise of the one with the given label
+    pub fn display_after(&self, label: u32, n: usize) -> impl Display + '_ {
+        LabelsDisplay {
+            cups: self,
+            label,
+            n,
+        }
+    }
+
+    /// Play the given number of moves, picking up `PICKUP` cups each time
+    pub fn play<const PICKUP: usize>(&mut self, moves: usize) {
+        // Otherwise there'd be no cup left to be the destination
+        assert!(
+            PICKUP + 1 < self.len(),
+            "picking up {} cups needs at least {} cups in the ring",
+            PICKUP,
+            PICKUP + 2
+        );
+
+        for _ in 0..moves {
+            self.play_round::<PICKUP>();
+        }
+    }
+
+    fn play_round<const PICKUP: usize>(&mut self) {
+        let successors = &mut self.successors[..];
+        let current_cup = self.current_cup;
+        let highest = successors.len() as u32 - 1;
+
+        macro_rules! get_mut {
+            ($idx:expr) => {
+                unsafe {
+                    match successors.get_mut($idx as usize) {
+                        Some(val) => val,
+                        None => std::hint::unreachable_unchecked(),
+                    }
+                }
+            };
+        }
+
+        // Pick up the cups after the current one
+        let mut hand = [0; PICKUP];
+
+        let past_hand = hand.iter_mut().fold(*get_mut!(current_cup), |prev, dest| {
+            let previous_successor = *get_mut!(prev);
+            *dest = prev;
+            previous_successor
+        });
+
+        *get_mut!(current_cup) = past_hand;
+
+        // Find the destination cup
+        let mut dest = current_cup;
         loop {
-            n = self.successors[n as usize];
-            if n == 1 {
+            dest = if dest == 1 { highest } else { dest - 1 };
+            if !hand.contains(&dest) {
                 break;
             }
-            write!(f, "{}", n)?;
         }
 
-        Ok(())
+        // Splice our hand into the cups after the destination cup
+        hand.iter().copied().fold(dest, |prev, cur| {
+            let prev = get_mut!(prev);
+            let skip = *prev;
+            *prev = cur;
+            *get_mut!(cur) = skip;
+            cur
+        });
+
+        // Move on to the cup after the current one
+        self.current_cup = *get_mut!(current_cup);
     }
 }
 
-fn play_round_part1(current_cup: u8, successors: &mut [u8; CARDS_PART1]) -> u8 {
-    // Pick up the cups after the current one
-    let mut hand = [0; PICKUP_NUM];
-    for dest in hand.iter_mut() {
-        let picked_up = successors[current_cup as usize];
-        let previous_successor = successors[picked_up as usize];
-        successors[current_cup as usize] = previous_successor;
-        *dest = picked_up;
+pub struct After<'a> {
+    successors: &'a [u32],
+    cup: u32,
+    remaining: usize,
+}
+
+impl Iterator for After<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        self.cup = self.successors[self.cup as usize];
+        Some(self.cup)
     }
 
-    // Find the destination cup
-    let dest = (1..=3)
-        .take_while(|&n| n < current_cup)
-        .map(|n| current_cup - n)
-        .chain(
-            (current_cup.checked_sub(4).unwrap_or(1)..successors.len() as u8)
-                .rev()
-                .filter(|&n| n != current_cup),
-        )
-        .find(|&n| !hand.iter().any(|&m| m == n))
-        .unwrap();
-
-    // Splice our hand into the cups after the destination cup
-    hand.iter().copied().fold(dest, |prev, cur| {
-        let skip = successors[prev as usize];
-        successors[prev as usize] = cur;
-        successors[cur as usize] = skip;
-        cur
-    });
-
-    // Return the new current cup
-    successors[current_cup as usize]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
 
-fn play_round_part2(current_cup: u32, successors: &mut [u32; CARDS_PART2]) -> u32 {
-    // Pick up the cups after the current one
-    let mut hand = [0; PICKUP_NUM];
+impl ExactSizeIterator for After<'_> {}
 
-    macro_rules! get_mut {
-        ($idx:expr) => {
-            unsafe {
-                match successors.get_mut($idx as usize) {
-                    Some(val) => val,
-                    None => std::hint::unreachable_unchecked(),
-                }
+struct LabelsDisplay<'a> {
+    cups: &'a Cups,
+    label: u32,
+    n: usize,
+}
+
+impl Display for LabelsDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Labels run together unless some of them have more than one digit
+        let separator = if self.cups.len() > 9 { " " } else { "" };
+
+        for (i, n) in self.cups.after(self.label).take(self.n).enumerate() {
+            if i != 0 {
+                f.write_str(separator)?;
             }
-        };
+            write!(f, "{}", n)?;
+        }
+
+        Ok(())
     }
+}
 
-    let past_hand = hand.iter_mut().fold(*get_mut!(current_cup), |prev, dest| {
-        let previous_successor = *get_mut!(prev);
-        *dest = prev;
-        previous_successor
-    });
-
-    *get_mut!(current_cup) = past_hand;
-
-    // Find the destination cup
-    let dest = (1..=3)
-        .take_while(|&n| n < current_cup)
-        .map(|n| current_cup - n)
-        .chain(
-            (current_cup.checked_sub(4).unwrap_or(1)..successors.len() as u32)
-                .rev()
-                .filter(|&n| n != current_cup),
-        )
-        .find(|&n| !hand.iter().any(|&m| m == n))
-        .unwrap();
-
-    // Splice our hand into the cups after the destination cup
-    hand.iter().copied().fold(dest, |prev, cur| {
-        let prev = get_mut!(prev);
-        let skip = *prev;
-        *prev = cur;
-        *get_mut!(cur) = skip;
-        cur
-    });
-
-    // Return the new current cup
-    *get_mut!(current_cup)
+/// The labels on the cups after cup 1
+pub struct CupsDisplay {
+    cups: Cups,
+}
+
+impl CupsDisplay {
+    pub fn cups(&self) -> &Cups {
+        &self.cups
+    }
+}
+
+impl Display for CupsDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.cups.display_after(1, self.cups.len()).fmt(f)
+    }
 }
 
 #[inline]
@@ -123,47 +233,18 @@ pub fn parse_input() -> Vec<u8> {
 }
 
 #[inline]
-pub fn solve_part1(cups: &[u8]) -> CupsDisplay {
-    let current_cup = cups[0];
-    let mut successors = [0; CARDS_PART1];
-    let last = cups.iter().skip(1).fold(current_cup, |prev, &cur| {
-        successors[prev as usize] = cur;
-        cur
-    });
-    successors[last as usize] = current_cup;
-
-    (0..MOVES_PART1).fold(current_cup, |current_cup, _| {
-        play_round_part1(current_cup, &mut successors)
-    });
-
-    CupsDisplay { successors }
+pub fn solve_part1(initial_cups: &[u8]) -> CupsDisplay {
+    let mut cups = Cups::new(initial_cups, CUPS_PART1);
+    cups.play::<PICKUP_NUM>(MOVES_PART1);
+    CupsDisplay { cups }
 }
 
 #[inline]
 pub fn solve_part2(initial_cups: &[u8]) -> u64 {
-    let current_cup = initial_cups[0] as u32;
-
-    // SAFETY: trust me
-    let mut successors: Box<[u32; CARDS_PART2]> = unsafe { Box::new_uninit().assume_init() };
-
-    let last = initial_cups
-        .iter()
-        .skip(1)
-        .map(|&n| n as u32)
-        .chain(CARDS_PART1 as u32..CARDS_PART2 as u32)
-        .fold(current_cup, |prev, cur| {
-            successors[prev as usize] = cur;
-            cur
-        });
-    successors[last as usize] = current_cup;
-
-    (0..MOVES_PART2).fold(current_cup, |current_cup, _| {
-        play_round_part2(current_cup, &mut successors)
-    });
+    let mut cups = Cups::new(initial_cups, CUPS_PART2);
+    cups.play::<PICKUP_NUM>(MOVES_PART2);
 
-    let a = successors[1];
-    let b = successors[a as usize];
-    a as u64 * b as u64
+    cups.after(1).take(2).map(u64::from).product()
 }
 
 #[inline]
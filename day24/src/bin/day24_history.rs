@@ -0,0 +1,24 @@
+//! Print the number of black tiles and their bounding box for each day as CSV
+//!
+//! Usage: day24_history [DAYS]
+
+use std::env;
+
+use day24::{parse_input, Simulation};
+
+fn main() {
+    let days = env::args()
+        .nth(1)
+        .map_or(100, |days| days.parse().expect("DAYS should be a number"));
+
+    println!("day,black,min_q,max_q,min_r,max_r");
+
+    for stats in Simulation::new(parse_input(), days) {
+        print!("{},{}", stats.day, stats.black);
+
+        match stats.bounds {
+            Some(bb) => println!(",{},{},{},{}", bb.min_q, bb.max_q, bb.min_r, bb.max_r),
+            None => println!(",,,,"),
+        }
+    }
+}
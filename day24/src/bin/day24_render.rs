@@ -0,0 +1,41 @@
+//! Draw the lobby floor after 100 days
+//!
+//! Usage: day24_render [svg|ascii] [FRAMES_DIR]
+//!
+//! If FRAMES_DIR is given, one frame per day is written there instead of
+//! printing the final floor to stdout.
+
+use std::{env, fs, path::Path};
+
+use day24::{parse_input, render, Simulation};
+
+const DAYS: usize = 100;
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let (draw, extension): (fn(&_) -> String, _) = match args.next().as_deref() {
+        None | Some("svg") => (render::svg, "svg"),
+        Some("ascii") => (render::ascii, "txt"),
+        Some(mode) => panic!("unknown mode {:?}, expected svg or ascii", mode),
+    };
+
+    let mut simulation = Simulation::new(parse_input(), DAYS);
+
+    match args.next() {
+        Some(dir) => {
+            let dir = Path::new(&dir);
+            fs::create_dir_all(dir).unwrap();
+
+            while let Some(stats) = simulation.next() {
+                let path = dir.join(format!("day{:03}.{}", stats.day, extension));
+                fs::write(path, draw(simulation.lobby().black_tiles())).unwrap();
+            }
+        }
+
+        None => {
+            simulation.by_ref().for_each(drop);
+            print!("{}", draw(simulation.lobby().black_tiles()));
+        }
+    }
+}
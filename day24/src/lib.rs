@@ -1,10 +1,14 @@
+use std::mem::swap;
+
 use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
 
+pub mod render;
+
 #[derive(Debug, Hash, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Hexagon {
     // Axial coordinates
-    q: i64,
-    r: i64,
+    pub q: i64,
+    pub r: i64,
 }
 
 impl Hexagon {
@@ -90,45 +94,160 @@ impl Hexagon {
     }
 }
 
-pub fn solve_part2(mut black: HashSet<Hexagon>) -> usize {
-    let mut next_state = HashSet::default();
-    let mut white: HashMap<Hexagon, usize> = HashMap::default();
+/// Smallest axial-coordinate rectangle containing every black tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min_q: i64,
+    pub max_q: i64,
+    pub min_r: i64,
+    pub max_r: i64,
+}
+
+impl BoundingBox {
+    pub fn of(black: &HashSet<Hexagon>) -> Option<Self> {
+        let mut it = black.iter();
+        let first = it.next()?;
+
+        Some(it.fold(
+            Self {
+                min_q: first.q,
+                max_q: first.q,
+                min_r: first.r,
+                max_r: first.r,
+            },
+            |bb, h| Self {
+                min_q: bb.min_q.min(h.q),
+                max_q: bb.max_q.max(h.q),
+                min_r: bb.min_r.min(h.r),
+                max_r: bb.max_r.max(h.r),
+            },
+        ))
+    }
+}
+
+/// The lobby floor, along with the scratch buffers needed to flip it day by day
+pub struct Lobby {
+    black: HashSet<Hexagon>,
+    next_state: HashSet<Hexagon>,
+    white: HashMap<Hexagon, usize>,
+}
+
+impl Lobby {
+    pub fn new(black: HashSet<Hexagon>) -> Self {
+        Self {
+            black,
+            next_state: HashSet::default(),
+            white: HashMap::default(),
+        }
+    }
+
+    pub fn black_tiles(&self) -> &HashSet<Hexagon> {
+        &self.black
+    }
 
-    for _ in 0..100 {
-        next_state.clear();
-        next_state.reserve(black.len() * 2);
-        white.reserve(black.len() * 8);
+    /// Flip the tiles according to the rules for a single day
+    pub fn advance(&mut self) {
+        self.next_state.clear();
+        self.next_state.reserve(self.black.len() * 2);
+        self.white.reserve(self.black.len() * 8);
 
-        for tile in black.iter() {
+        for tile in self.black.iter() {
             let mut active_neighbors = 0;
 
             for &neighbor in tile.neighbors().iter() {
-                if black.contains(&neighbor) {
+                if self.black.contains(&neighbor) {
                     active_neighbors += 1;
                 } else {
-                    *white.entry(neighbor).or_default() += 1;
+                    *self.white.entry(neighbor).or_default() += 1;
                 }
             }
 
             if active_neighbors == 1 || active_neighbors == 2 {
-                next_state.insert(*tile);
+                self.next_state.insert(*tile);
             }
         }
 
-        next_state.extend(
-            white
+        self.next_state.extend(
+            self.white
                 .drain()
                 .filter(|&(_, neighbors)| neighbors == 2)
                 .map(|(tile, _)| tile),
         );
 
-        black.clone_from(&next_state);
+        swap(&mut self.black, &mut self.next_state);
     }
-    black.len()
 }
 
-#[inline]
-pub fn solve() -> (usize, usize) {
+/// The state of the floor at the end of a given day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayStats {
+    pub day: usize,
+    pub black: usize,
+    pub bounds: Option<BoundingBox>,
+}
+
+/// Iterator over the state of the floor, starting with the initial layout as
+/// day 0 and stopping after the given number of days
+pub struct Simulation {
+    lobby: Lobby,
+    day: usize,
+    days: usize,
+}
+
+impl Simulation {
+    pub fn new(black: HashSet<Hexagon>, days: usize) -> Self {
+        Self {
+            lobby: Lobby::new(black),
+            day: 0,
+            days,
+        }
+    }
+
+    pub fn lobby(&self) -> &Lobby {
+        &self.lobby
+    }
+}
+
+impl Iterator for Simulation {
+    type Item = DayStats;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.day > self.days {
+            return None;
+        }
+
+        if self.day != 0 {
+            self.lobby.advance();
+        }
+
+        let black = self.lobby.black_tiles();
+        let stats = DayStats {
+            day: self.day,
+            black: black.len(),
+            bounds: BoundingBox::of(black),
+        };
+
+        self.day += 1;
+        Some(stats)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.days + 1).saturating_sub(self.day);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Simulation {}
+
+pub fn solve_part2(black: HashSet<Hexagon>, days: usize) -> usize {
+    let mut lobby = Lobby::new(black);
+    for _ in 0..days {
+        lobby.advance();
+    }
+    lobby.black_tiles().len()
+}
+
+pub fn parse_input() -> HashSet<Hexagon> {
     let mut black = HashSet::default();
 
     include_str!("input.txt")
@@ -142,8 +261,15 @@ pub fn solve() -> (usize, usize) {
             }
         });
 
+    black
+}
+
+#[inline]
+pub fn solve() -> (usize, usize) {
+    let black = parse_input();
+
     let part1 = black.len();
-    let part2 = solve_part2(black);
+    let part2 = solve_part2(black, 100);
 
     (part1, part2)
 }
@@ -0,0 +1,119 @@
+use std::fmt::Write;
+
+use rustc_hash::FxHashSet as HashSet;
+
+use crate::Hexagon;
+
+/// Circumradius of a hexagon in the SVG output
+const HEX_SIZE: f64 = 10.0;
+
+/// Center of the given (pointy-top) hexagon in pixel coordinates
+fn center(h: Hexagon) -> (f64, f64) {
+    let x = HEX_SIZE * 3f64.sqrt() * (h.q as f64 + h.r as f64 / 2.0);
+    let y = HEX_SIZE * 1.5 * h.r as f64;
+    (x, y)
+}
+
+/// Draw the black tiles as an SVG hex map
+pub fn svg(black: &HashSet<Hexagon>) -> String {
+    // The reference tile is always in frame, which also keeps the empty floor well-defined
+    let (mut min_x, mut min_y) = (0f64, 0f64);
+    let (mut max_x, mut max_y) = (0f64, 0f64);
+
+    for (x, y) in black.iter().map(|&h| center(h)) {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+
+    min_x -= HEX_SIZE * 2.0;
+    min_y -= HEX_SIZE * 2.0;
+    max_x += HEX_SIZE * 2.0;
+    max_y += HEX_SIZE * 2.0;
+
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+        min_x,
+        min_y,
+        max_x - min_x,
+        max_y - min_y
+    )
+    .unwrap();
+
+    writeln!(
+        out,
+        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="white"/>"#,
+        min_x,
+        min_y,
+        max_x - min_x,
+        max_y - min_y
+    )
+    .unwrap();
+
+    // Sort the tiles so that the same floor always renders to the same file
+    let mut tiles = black.iter().copied().collect::<Vec<_>>();
+    tiles.sort_unstable_by_key(|h| (h.r, h.q));
+
+    for tile in tiles {
+        let (x, y) = center(tile);
+
+        out.push_str(r#"<polygon fill="black" stroke="grey" points=""#);
+        for i in 0..6 {
+            let angle = (60.0 * i as f64 - 30.0).to_radians();
+            write!(
+                out,
+                "{:.2},{:.2} ",
+                x + HEX_SIZE * angle.cos(),
+                y + HEX_SIZE * angle.sin()
+            )
+            .unwrap();
+        }
+        out.pop();
+        out.push_str("\"/>\n");
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Draw the black tiles as text, shifting every row by half a tile so that
+/// each tile sits between the two tiles above it
+pub fn ascii(black: &HashSet<Hexagon>) -> String {
+    if black.is_empty() {
+        return String::new();
+    }
+
+    // Doubled horizontal coordinate: east is +2, south-east is +1
+    let column = |h: &Hexagon| 2 * h.q + h.r;
+
+    let min_r = black.iter().map(|h| h.r).min().unwrap();
+    let max_r = black.iter().map(|h| h.r).max().unwrap();
+    let min_col = black.iter().map(column).min().unwrap();
+    let max_col = black.iter().map(column).max().unwrap();
+
+    let mut out = String::new();
+
+    for r in min_r..=max_r {
+        for col in min_col..=max_col {
+            if (col - r).rem_euclid(2) != 0 {
+                out.push(' ');
+                continue;
+            }
+
+            let h = Hexagon {
+                q: (col - r) / 2,
+                r,
+            };
+            out.push(if black.contains(&h) { '#' } else { '.' });
+        }
+
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+    }
+
+    out
+}
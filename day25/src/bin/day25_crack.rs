@@ -0,0 +1,60 @@
+//! Recover both loop sizes of a handshake with any base and prime modulus, and
+//! check that the card and the door end up with the same encryption key
+//!
+//! Usage: day25_crack [BASE MODULUS CARD_PUBKEY DOOR_PUBKEY]
+//!
+//! Without arguments, cracks the puzzle input.
+
+use std::env;
+
+use day25::{parse_input, Handshake};
+
+fn main() {
+    let args: Vec<u64> = env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("arguments should be numbers"))
+        .collect();
+
+    let (handshake, card_pubkey, door_pubkey) = match args[..] {
+        [] => {
+            let (card_pubkey, door_pubkey) = parse_input();
+            (Handshake::default(), card_pubkey, door_pubkey)
+        }
+        [base, modulus, card_pubkey, door_pubkey] => {
+            assert!(modulus > 2, "the modulus should be an odd prime");
+            (Handshake::new(base, modulus), card_pubkey, door_pubkey)
+        }
+        _ => panic!("usage: day25_crack [BASE MODULUS CARD_PUBKEY DOOR_PUBKEY]"),
+    };
+
+    match handshake.factors() {
+        Some(factors) => {
+            let factors: Vec<_> = factors
+                .iter()
+                .map(|&(q, e)| match e {
+                    1 => q.to_string(),
+                    _ => format!("{}^{}", q, e),
+                })
+                .collect();
+            println!("method: Pohlig-Hellman over {}", factors.join(" * "));
+        }
+        None => println!("method: baby-step giant-step"),
+    }
+
+    let cracked = match handshake.crack(card_pubkey, door_pubkey) {
+        Ok(cracked) => cracked,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    println!("card loop size: {}", cracked.card_loop_size);
+    println!("door loop size: {}", cracked.door_loop_size);
+    println!("card encryption key: {}", cracked.card_key);
+    println!("door encryption key: {}", cracked.door_key);
+    println!(
+        "keys {}",
+        if cracked.agree() { "agree" } else { "DISAGREE" }
+    );
+}
@@ -1,59 +1,151 @@
 #![allow(clippy::inconsistent_digit_grouping)]
 
-use rustc_hash::FxHashMap as HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
 
 const BASE: u64 = 7;
 const MODULUS: u64 = 2020_12_27;
 
-fn transform(loop_size: u64, subject: u64) -> u64 {
-    powmod(subject, loop_size)
+/// If every prime factor of the modulus minus one is below this, we crack the
+/// keys with Pohlig-Hellman instead of a plain baby-step giant-step
+const SMOOTHNESS_BOUND: u64 = 1 << 20;
+
+/// The handshake's parameters: public keys are the base raised to the power of
+/// a secret loop size, modulo a prime
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    base: u64,
+    modulus: u64,
+    /// The factorization of `modulus - 1`, if it's smooth
+    factors: Option<Vec<(u64, u32)>>,
 }
 
-fn powmod(x: u64, mut y: u64) -> u64 {
-    let mut t = 1;
-    let mut tmp = x % MODULUS;
-    while y > 0 {
-        if y & 1 > 0 {
-            t = t * tmp % MODULUS;
-        }
+/// Both loop sizes recovered from a pair of public keys, along with the
+/// encryption key each side would derive from them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cracked {
+    pub card_loop_size: u64,
+    pub door_loop_size: u64,
+    pub card_key: u64,
+    pub door_key: u64,
+}
 
-        tmp = (tmp * tmp) % MODULUS;
-        y >>= 1;
+impl Cracked {
+    /// Did both sides derive the same encryption key?
+    pub fn agree(&self) -> bool {
+        self.card_key == self.door_key
     }
-    t
 }
 
-fn babystep_giantstep(pubkey: u64) -> Option<u64> {
-    let m = (MODULUS as f64).sqrt().ceil() as u64;
+/// Why we couldn't recover a loop size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrackError {
+    /// No loop size turns the base into this public key
+    NoLoopSize(u64),
+    /// The modulus minus one isn't smooth, and the modulus is too large for
+    /// baby-step giant-step's table to fit in memory
+    ModulusTooLarge(u64),
+}
 
-    let mut table = HashMap::<u64, u64>::with_capacity_and_hasher(m as usize, Default::default());
-    let mut e = 1;
+impl Display for CrackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrackError::NoLoopSize(pubkey) => {
+                write!(f, "no loop size produces the public key {}", pubkey)
+            }
+            CrackError::ModulusTooLarge(modulus) => write!(
+                f,
+                "modulus {} is too large for baby-step giant-step",
+                modulus
+            ),
+        }
+    }
+}
 
-    for i in 0..m {
-        table.insert(e, i);
-        e = (e * BASE) % MODULUS;
+impl Error for CrackError {}
+
+/// There's no second puzzle on the last day, so part 2 shows how we got the
+/// answer to part 1 instead
+impl Display for Cracked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "card loop size {}, door loop size {}, keys {}",
+            self.card_loop_size,
+            self.door_loop_size,
+            if self.agree() { "agree" } else { "disagree" }
+        )
     }
+}
 
-    let factor = powmod(BASE, MODULUS - m - 1);
-    e = pubkey;
-    for i in 0..m {
-        if let Some(x) = table.get(&e) {
-            return Some(i * m + x);
+impl Handshake {
+    /// The modulus must be prime
+    pub fn new(base: u64, modulus: u64) -> Self {
+        Self {
+            base,
+            modulus,
+            factors: numtheory::smooth_factors(modulus - 1, SMOOTHNESS_BOUND),
         }
-        e = (e * factor) % MODULUS;
     }
-    None
+
+    /// The factorization of the modulus minus one, if we're using Pohlig-Hellman
+    pub fn factors(&self) -> Option<&[(u64, u32)]> {
+        self.factors.as_deref()
+    }
+
+    pub fn transform(&self, loop_size: u64, subject: u64) -> u64 {
+        numtheory::pow_mod(subject, loop_size, self.modulus)
+    }
+
+    /// The smallest loop size that turns the base into the given public key
+    pub fn loop_size(&self, pubkey: u64) -> Result<u64, CrackError> {
+        let loop_size = match &self.factors {
+            Some(factors) => numtheory::pohlig_hellman(self.base, pubkey, self.modulus, factors),
+            None => {
+                let max_order = numtheory::MAX_BABY_STEPS * numtheory::MAX_BABY_STEPS;
+                if self.modulus - 1 > max_order {
+                    return Err(CrackError::ModulusTooLarge(self.modulus));
+                }
+                numtheory::discrete_log(self.base, pubkey, self.modulus)
+            }
+        };
+
+        loop_size.ok_or(CrackError::NoLoopSize(pubkey))
+    }
+
+    pub fn crack(&self, card_pubkey: u64, door_pubkey: u64) -> Result<Cracked, CrackError> {
+        let card_loop_size = self.loop_size(card_pubkey)?;
+        let door_loop_size = self.loop_size(door_pubkey)?;
+
+        Ok(Cracked {
+            card_loop_size,
+            door_loop_size,
+            card_key: self.transform(card_loop_size, door_pubkey),
+            door_key: self.transform(door_loop_size, card_pubkey),
+        })
+    }
+}
+
+impl Default for Handshake {
+    /// The parameters used by the puzzle
+    fn default() -> Self {
+        Self::new(BASE, MODULUS)
+    }
+}
+
+pub fn parse_input() -> (u64, u64) {
+    let mut it = include_str!("input.txt")
+        .lines()
+        .map(|n| n.parse::<u64>().unwrap());
+    (it.next().unwrap(), it.next().unwrap())
 }
 
 #[inline]
-pub fn solve() -> u64 {
-    let (card_pubkey, door_pubkey) = {
-        let mut it = include_str!("input.txt")
-            .lines()
-            .map(|n| n.parse::<u64>().unwrap());
-        (it.next().unwrap(), it.next().unwrap())
-    };
-
-    let card_loopsize = babystep_giantstep(card_pubkey).unwrap();
-    transform(card_loopsize, door_pubkey)
+pub fn solve() -> (u64, Cracked) {
+    let (card_pubkey, door_pubkey) = parse_input();
+
+    let cracked = Handshake::default()
+        .crack(card_pubkey, door_pubkey)
+        .unwrap();
+    (cracked.card_key, cracked)
 }
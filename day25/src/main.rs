@@ -1,4 +1,5 @@
 fn main() {
-    let part1 = day25::solve();
+    let (part1, part2) = day25::solve();
     println!("{}", part1);
+    println!("{}", part2);
 }
@@ -0,0 +1,237 @@
+//! Modular arithmetic shared between the days that need it. Everything works
+//! on `u64`, widening to 128 bits wherever a product could overflow.
+
+use std::convert::TryFrom;
+
+use rustc_hash::FxHashMap as HashMap;
+
+/// `a * b mod m`, without overflowing
+#[inline]
+pub fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+}
+
+/// `base` to the power of `exp`, modulo `m`, by repeated squaring
+pub fn pow_mod(base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = base % m;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+
+    result
+}
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Find `(g, x, y)` such that `a * x + b * y = g`, where `g` is the greatest
+/// common divisor of `a` and `b`
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+
+    (old_r, old_x, old_y)
+}
+
+/// The inverse of `a` modulo `m`, or `None` if they aren't coprime
+pub fn inverse(a: u64, m: u64) -> Option<u64> {
+    let (g, x, _) = extended_gcd(i128::from(a % m), i128::from(m));
+    if g != 1 {
+        return None;
+    }
+
+    Some(x.rem_euclid(i128::from(m)) as u64)
+}
+
+/// Solve a system of congruences `x ≡ residue (mod modulus)`, returning the
+/// smallest non-negative solution along with the modulus it's unique under,
+/// which is the least common multiple of all the moduli.
+///
+/// The moduli don't need to be coprime. Returns `None` if the congruences
+/// contradict each other, and panics if the combined modulus doesn't fit in a
+/// `u64`.
+pub fn crt<I>(congruences: I) -> Option<(u64, u64)>
+where
+    I: IntoIterator<Item = (i64, u64)>,
+{
+    congruences
+        .into_iter()
+        .try_fold((0, 1), |(x, m), (residue, modulus)| {
+            let residue = i128::from(residue).rem_euclid(i128::from(modulus));
+            let (x, m, modulus) = (i128::from(x), i128::from(m), i128::from(modulus));
+
+            // We need x + m * k ≡ residue (mod modulus), i.e.
+            // m * k ≡ residue - x (mod modulus), which only has solutions if
+            // the gcd divides the right hand side
+            let (g, m_inv, _) = extended_gcd(m, modulus);
+            let diff = residue - x;
+            if diff % g != 0 {
+                return None;
+            }
+
+            let step = modulus / g;
+            let k = (diff / g).rem_euclid(step) * m_inv.rem_euclid(step) % step;
+            let lcm = u64::try_from(m * step).expect("combined modulus overflows a u64");
+
+            Some(((x + m * k) as u64 % lcm, lcm))
+        })
+}
+
+/// The most baby steps `discrete_log` will take, which keeps its table to a few
+/// hundred megabytes. That's enough for any modulus up to 2^48.
+pub const MAX_BABY_STEPS: u64 = 1 << 24;
+
+/// Find the smallest `x` such that `base` to the power of `x` is `target`
+/// modulo the prime `modulus`, using baby-step giant-step. Panics if the
+/// modulus is over `MAX_BABY_STEPS` squared, since the table wouldn't fit in
+/// memory.
+pub fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    // The powers of `base` repeat every `modulus - 1` steps at the most
+    let order = modulus.saturating_sub(1).max(1);
+    assert!(
+        order <= MAX_BABY_STEPS * MAX_BABY_STEPS,
+        "modulus {} is too large for baby-step giant-step",
+        modulus
+    );
+    baby_giant(base, target, modulus, order)
+}
+
+/// Baby-step giant-step, only looking for exponents below `order`
+fn baby_giant(base: u64, target: u64, modulus: u64, order: u64) -> Option<u64> {
+    let (base, target) = (base % modulus, target % modulus);
+    let m = (order as f64).sqrt().ceil() as u64;
+
+    // Keep the smallest exponent for each power, so that the first match we
+    // find is the smallest solution
+    let mut table = HashMap::with_capacity_and_hasher(m as usize, Default::default());
+    let mut e = 1 % modulus;
+    for j in 0..m {
+        table.entry(e).or_insert(j);
+        e = mul_mod(e, base, modulus);
+    }
+
+    // Each giant step divides the target by `base` to the `m`th power
+    let factor = inverse(pow_mod(base, m, modulus), modulus);
+    let mut e = target;
+    for i in 0..m {
+        if let Some(j) = table.get(&e) {
+            return Some(i * m + j);
+        }
+        e = mul_mod(e, factor?, modulus);
+    }
+
+    None
+}
+
+/// Factorize `n` into primes and their exponents, if all of its prime factors
+/// are below `bound`
+pub fn smooth_factors(mut n: u64, bound: u64) -> Option<Vec<(u64, u32)>> {
+    let mut factors = Vec::new();
+
+    let mut p = 2;
+    while p < bound && p <= n / p {
+        let mut exp = 0;
+        loop {
+            let (quotient, remainder) = (n / p, n % p);
+            if remainder != 0 {
+                break;
+            }
+            n = quotient;
+            exp += 1;
+        }
+        if exp > 0 {
+            factors.push((p, exp));
+        }
+        p += 1;
+    }
+
+    // Whatever is left has no factors below its square root, so it's prime
+    if n > 1 {
+        if n >= bound {
+            return None;
+        }
+        factors.push((n, 1));
+    }
+
+    Some(factors)
+}
+
+/// Same as `discrete_log`, but using Pohlig-Hellman given the factorization of
+/// `modulus - 1`. This only needs a baby-step giant-step per prime factor, so
+/// it's much faster when they're all small.
+pub fn pohlig_hellman(base: u64, target: u64, modulus: u64, factors: &[(u64, u32)]) -> Option<u64> {
+    let (base, target) = (base % modulus, target % modulus);
+
+    // Only a multiple of the modulus has no inverse, and its powers aren't a
+    // group: they're 1 and then 0 forever
+    let base_inv = match inverse(base, modulus) {
+        Some(base_inv) => base_inv,
+        None if target == 1 % modulus => return Some(0),
+        None if target == 0 => return Some(1),
+        None => return None,
+    };
+
+    // `base` might not generate the whole group, so work out its order, which
+    // divides `modulus - 1`, and how many times each prime divides it
+    let mut order = modulus - 1;
+    let exponents: Vec<_> = factors
+        .iter()
+        .map(|&(q, e)| {
+            let mut e = e;
+            while e > 0 && pow_mod(base, order / q, modulus) == 1 {
+                order /= q;
+                e -= 1;
+            }
+            (q, e)
+        })
+        .collect();
+
+    // Find x modulo each prime power q^e one base-q digit at a time, raising
+    // everything to the power of order / q^(k + 1) to land in the subgroup of
+    // order q
+    let congruences = exponents.iter().map(|&(q, e)| {
+        let gamma = pow_mod(base, order / q, modulus);
+        let mut x = 0;
+        let mut q_k = 1;
+
+        for _ in 0..e {
+            let rest = mul_mod(target, pow_mod(base_inv, x, modulus), modulus);
+            let h = pow_mod(rest, order / (q_k * q), modulus);
+            x += baby_giant(gamma, h, modulus, q)? * q_k;
+            q_k *= q;
+        }
+
+        Some((i64::try_from(x).ok()?, q_k))
+    });
+
+    let (x, _) = crt(congruences.collect::<Option<Vec<_>>>()?)?;
+
+    // The digits only tell us about the subgroup generated by `base`, so check
+    // the target is actually in it
+    if pow_mod(base, x, modulus) == target {
+        Some(x)
+    } else {
+        None
+    }
+}